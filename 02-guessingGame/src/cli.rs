@@ -0,0 +1,108 @@
+//! Command-line interface definition.
+
+use std::{env, path::PathBuf};
+
+use clap::{Parser, ValueEnum};
+
+use crate::{scores, Settings};
+
+#[derive(Parser, Debug)]
+#[command(name = "guessing_game", version, about = "Guess the number!")]
+pub struct Cli {
+    /// Preset range and number of attempts; --min, --max and --attempts
+    /// override parts of it
+    #[arg(short, long, value_enum, default_value_t = Difficulty::Normal)]
+    pub difficulty: Difficulty,
+
+    /// Smallest number the secret can be
+    #[arg(long)]
+    pub min: Option<u32>,
+
+    /// Largest number the secret can be
+    #[arg(long)]
+    pub max: Option<u32>,
+
+    /// How many guesses you get before you lose
+    #[arg(short, long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub attempts: Option<u32>,
+
+    /// Pick the secret number from a random generator seeded with this, so
+    /// that the same seed always gives the same game. Seeded games don't
+    /// count for the high scores
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Name to put on the high-score table [default: $USER]
+    #[arg(long)]
+    pub name: Option<String>,
+
+    /// Print the high-score table instead of playing
+    #[arg(long, conflicts_with_all = ["solve", "benchmark"])]
+    pub scores: bool,
+
+    /// Swap roles: you think of a number in the range and answer the
+    /// program's guesses with "Too small!" or "Too big!"
+    #[arg(long, conflicts_with = "benchmark")]
+    pub solve: bool,
+
+    /// Let the solver guess every number in the range and check it never
+    /// needs more guesses than binary search allows. The range may hold at
+    /// most 1000000 numbers
+    #[arg(long)]
+    pub benchmark: bool,
+
+    /// File to keep the high scores in
+    #[arg(long, value_name = "PATH", default_value = scores::DEFAULT_FILE)]
+    pub score_file: PathBuf,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difficulty {
+    /// 1 to 50, 10 attempts
+    Easy,
+    /// 1 to 100, 7 attempts
+    Normal,
+    /// 1 to 1000, 10 attempts
+    Hard,
+}
+
+impl Difficulty {
+    /// The settings of the preset. Normal and hard give exactly as many
+    /// attempts as halving the range each time needs.
+    pub fn settings(self) -> Settings {
+        match self {
+            Difficulty::Easy => Settings::new(1..=50, 10),
+            Difficulty::Normal => Settings::new(1..=100, 7),
+            Difficulty::Hard => Settings::new(1..=1000, 10),
+        }
+    }
+}
+
+impl Cli {
+    /// The name to record wins under, cleaned up for the score file, or
+    /// "anonymous" if nothing printable is left of it.
+    pub fn player(&self) -> String {
+        self.name
+            .clone()
+            .or_else(|| env::var("USER").ok())
+            .map(|name| scores::clean_name(&name))
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| String::from("anonymous"))
+    }
+
+    /// The settings of the chosen difficulty, with the options given on top.
+    /// Fails if that leaves the range empty.
+    pub fn settings(&self) -> Result<Settings, String> {
+        let preset = self.difficulty.settings();
+        let min = self.min.unwrap_or(*preset.range.start());
+        let max = self.max.unwrap_or(*preset.range.end());
+
+        if min > max {
+            return Err(format!("the range {}..={} is empty", min, max));
+        }
+        Ok(Settings::new(
+            min..=max,
+            self.attempts.unwrap_or(preset.attempts),
+        ))
+    }
+}
@@ -0,0 +1,153 @@
+// https://doc.rust-lang.org/stable/book/ch02-00-guessing-game-tutorial.html
+//! The guessing game, separated from the terminal: [`play`] takes the random
+//! number generator to pick the secret with and the input and output to talk
+//! to the player through, so a game can be replayed exactly.
+//!
+//! ```
+//! use guessing_game::{play, Outcome, Settings};
+//! use rand::{rngs::StdRng, Rng, SeedableRng};
+//!
+//! let settings = Settings::new(1..=10, 3);
+//! // The same seed picks the same secret.
+//! let secret = StdRng::seed_from_u64(7).gen_range(settings.range.clone());
+//!
+//! let mut output = Vec::new();
+//! let input = format!("{}\n", secret);
+//! let outcome = play(&settings, &mut StdRng::seed_from_u64(7), input.as_bytes(), &mut output);
+//!
+//! assert_eq!(Outcome::Won { attempts: 1 }, outcome.unwrap());
+//! assert!(String::from_utf8(output).unwrap().ends_with("You win!\n"));
+//! ```
+
+use rand::Rng;
+use std::cmp::Ordering;
+use std::io::{self, BufRead, Write};
+use std::ops::RangeInclusive;
+
+pub mod cli;
+pub mod scores;
+pub mod solver;
+
+/// What a game is played with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Settings {
+    /// The numbers the secret is picked from.
+    pub range: RangeInclusive<u32>,
+    /// How many guesses the player gets before they lose.
+    pub attempts: u32,
+}
+
+impl Settings {
+    pub fn new(range: RangeInclusive<u32>, attempts: u32) -> Settings {
+        Settings { range, attempts }
+    }
+}
+
+/// How a game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The player guessed the secret with their `attempts`th guess.
+    Won { attempts: u32 },
+    /// The player ran out of attempts.
+    Lost { secret: u32 },
+    /// The input ended before the game did.
+    Quit { secret: u32 },
+}
+
+/// Plays one game: picks the secret from `settings.range` with `rng`, then
+/// reads guesses from `input`, one per line, and answers them on `output`.
+///
+/// Lines that aren't a number, and numbers outside the range, are skipped
+/// without costing an attempt.
+pub fn play(
+    settings: &Settings,
+    rng: &mut impl Rng,
+    mut input: impl BufRead,
+    mut output: impl Write,
+) -> io::Result<Outcome> {
+    let (min, max) = (*settings.range.start(), *settings.range.end());
+
+    writeln!(output, "Guess the number!")?;
+    writeln!(
+        output,
+        "It is between {} and {}, and you have {} attempts.",
+        min, max, settings.attempts
+    )?;
+
+    let secret_number = rng.gen_range(settings.range.clone());
+    let mut attempts_used = 0;
+
+    loop {
+        writeln!(output, "Please input your guess.")?;
+
+        let mut guess = String::new();
+
+        // references are immutable by default
+        // &mut means mutable references
+        // read_line puts whatever the user inputs into the string, and also
+        // returns a value - io::Result
+        // The ? hands a failure to read back to the caller, which decides
+        // what to do about it.
+        let read = input.read_line(&mut guess)?;
+
+        // Reading nothing at all means the input has ended, and no more
+        // guesses are coming.
+        if read == 0 {
+            writeln!(output, "The secret number was {}.", secret_number)?;
+            return Ok(Outcome::Quit {
+                secret: secret_number,
+            });
+        }
+
+        // If parse is able to successfully turn the string into a number, it
+        // will return an Ok value that contains the resulting number. That Ok
+        // value will match the first arm’s pattern, and the match expression
+        // will just return the num value that parse produced and put inside the
+        // Ok value. That number will end up right where we want it in the new
+        // guess variable we’re creating.
+
+        // The underscore, _, is a catchall value; in this example, we’re saying
+        // we want to match all Err values, no matter what information they have
+        // inside them. So the program will execute the second arm’s code,
+        // continue, which tells the program to go to the next iteration of the
+        // loop and ask for another guess.
+        let guess: u32 = match guess.trim().parse() {
+            Ok(num) => num,
+            Err(_) => continue,
+        };
+
+        // A guess that can't be right doesn't cost an attempt.
+        if !settings.range.contains(&guess) {
+            writeln!(output, "The secret number is between {} and {}.", min, max)?;
+            continue;
+        }
+
+        // This line prints the string that now contains the user’s input. The {}
+        // set of curly brackets is a placeholder
+        writeln!(output, "You guessed: {}", guess)?;
+        attempts_used += 1;
+
+        match guess.cmp(&secret_number) {
+            Ordering::Less => writeln!(output, "Too small!")?,
+            Ordering::Greater => writeln!(output, "Too big!")?,
+            Ordering::Equal => {
+                writeln!(output, "You win!")?;
+                return Ok(Outcome::Won {
+                    attempts: attempts_used,
+                });
+            }
+        }
+
+        if attempts_used == settings.attempts {
+            writeln!(output, "You lose! The secret number was {}.", secret_number)?;
+            return Ok(Outcome::Lost {
+                secret: secret_number,
+            });
+        }
+        writeln!(
+            output,
+            "Attempts left: {}",
+            settings.attempts - attempts_used
+        )?;
+    }
+}
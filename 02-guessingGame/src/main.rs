@@ -1,57 +1,113 @@
-// https://doc.rust-lang.org/stable/book/ch02-00-guessing-game-tutorial.html
-use rand::Rng;
-use std::cmp::Ordering;
-use std::io;
+use clap::{error::ErrorKind, CommandFactory, Parser};
+use guessing_game::{
+    cli::Cli,
+    scores::{self, Score, ScoreFile},
+    solver, Outcome,
+};
+use rand::{rngs::StdRng, SeedableRng};
+use std::{io, process, time::Instant};
 
 fn main() {
-    println!("Guess the number!");
-
-    let secret_number = rand::thread_rng().gen_range(1..101);
-
-    loop {
-        println!("Please input your guess.");
-
-        let mut guess = String::new();
-
-        io::stdin()
-            // references are immutable by default
-            // &mut means mutable references
-            // read_line puts whatever the user inputs into the string, and also
-            // returns a value - io::Result
-            .read_line(&mut guess)
-            // handling potential failure with result type
-            // if there's no expect, Rust warns that you haven’t used the Result value returned from
-            // read_line, indicating that the program hasn’t handled a possible
-            // error.
-            .expect("Failed to read line");
-        // If parse is able to successfully turn the string into a number, it
-        // will return an Ok value that contains the resulting number. That Ok
-        // value will match the first arm’s pattern, and the match expression
-        // will just return the num value that parse produced and put inside the
-        // Ok value. That number will end up right where we want it in the new
-        // guess variable we’re creating.
-
-        // The underscore, _, is a catchall value; in this example, we’re saying
-        // we want to match all Err values, no matter what information they have
-        // inside them. So the program will execute the second arm’s code,
-        // continue, which tells the program to go to the next iteration of the
-        // loop and ask for another guess.
-        let guess: u32 = match guess.trim().parse() {
-            Ok(num) => num,
-            Err(_) => continue,
+    // clap prints usage errors and --help itself and exits.
+    let cli = Cli::parse();
+    let score_file = ScoreFile::new(&cli.score_file);
+
+    if cli.scores {
+        if let Err(e) = print_scores(&score_file) {
+            eprintln!("Failed to read {}: {}", score_file.path().display(), e);
+            process::exit(1);
+        }
+        return;
+    }
+
+    let settings = cli.settings().unwrap_or_else(|message| {
+        Cli::command()
+            .error(ErrorKind::ArgumentConflict, message)
+            .exit()
+    });
+
+    if cli.benchmark {
+        let numbers = u64::from(settings.range.end() - settings.range.start()) + 1;
+        if numbers > solver::MAX_BENCHMARK_NUMBERS {
+            Cli::command()
+                .error(
+                    ErrorKind::ValueValidation,
+                    format!(
+                        "the benchmark plays every number in the range, so it may hold at most {} numbers, not {}",
+                        solver::MAX_BENCHMARK_NUMBERS,
+                        numbers
+                    ),
+                )
+                .exit();
+        }
+
+        let result = solver::benchmark(settings.range.clone());
+        println!(
+            "Solved all {} numbers from {} to {}: at most {} guesses ({:.2} on average), bound {}.",
+            result.games,
+            settings.range.start(),
+            settings.range.end(),
+            result.worst,
+            result.average,
+            result.bound
+        );
+        if !result.within_bound() {
+            process::exit(1);
+        }
+        return;
+    }
+
+    if cli.solve {
+        if let Err(e) = solver::solve(settings.range, io::stdin().lock(), io::stdout()) {
+            eprintln!("Failed to play: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
+    let mut rng = match cli.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+
+    let started = Instant::now();
+    let outcome = match guessing_game::play(&settings, &mut rng, io::stdin().lock(), io::stdout()) {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            eprintln!("Failed to play: {}", e);
+            process::exit(1);
+        }
+    };
+
+    // With a seed the secret is known in advance, so it wouldn't be a fair
+    // score.
+    if let (Outcome::Won { attempts }, None) = (outcome, cli.seed) {
+        let score = Score {
+            name: cli.player(),
+            attempts,
+            range: settings.range,
+            time: started.elapsed(),
         };
 
-        // This line prints the string that now contains the user’s input. The {}
-        // set of curly brackets is a placeholder
-        println!("You guessed: {}", guess);
-
-        match guess.cmp(&secret_number) {
-            Ordering::Less => println!("Too small!"),
-            Ordering::Greater => println!("Too big!"),
-            Ordering::Equal => {
-                println!("You win!");
-                break;
-            }
+        // Losing the score isn't worth failing a game that was won.
+        if let Err(e) = score_file.record(&score) {
+            eprintln!(
+                "Failed to save your score to {}: {}",
+                score_file.path().display(),
+                e
+            );
         }
     }
 }
+
+fn print_scores(score_file: &ScoreFile) -> io::Result<()> {
+    let scores = score_file.load()?;
+    if scores.skipped > 0 {
+        eprintln!(
+            "Skipped {} unreadable lines in {}",
+            scores.skipped,
+            score_file.path().display()
+        );
+    }
+    scores::write_leaderboard(&scores, io::stdout())
+}
@@ -0,0 +1,187 @@
+//! The high-score table, kept in a local text file.
+//!
+//! Every win is one line of tab-separated fields: name, attempts, the lowest
+//! and highest possible number, and the time taken in milliseconds. Each
+//! line is appended with a single write to a file opened in append mode, so
+//! games finishing at the same time don't interleave their lines. Lines that
+//! can't be read back, e.g. a line another game is still writing or a file
+//! that was edited by hand, are skipped rather than failing the whole table.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Where scores are kept unless `--score-file` says otherwise.
+pub const DEFAULT_FILE: &str = "guessing_game_scores.tsv";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Score {
+    pub name: String,
+    pub attempts: u32,
+    pub range: RangeInclusive<u32>,
+    pub time: Duration,
+}
+
+/// Makes `name` fit in a score line: control characters such as tabs and line
+/// breaks would break up the fields, so they become spaces, and the result is
+/// trimmed. May leave nothing at all.
+pub fn clean_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    name.trim().to_string()
+}
+
+impl Score {
+    fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}\n",
+            clean_name(&self.name),
+            self.attempts,
+            self.range.start(),
+            self.range.end(),
+            self.time.as_millis()
+        )
+    }
+
+    fn from_line(line: &str) -> Option<Score> {
+        let mut fields = line.split('\t');
+        let name = fields.next()?.to_string();
+        let attempts = fields.next()?.parse().ok()?;
+        let min = fields.next()?.parse().ok()?;
+        let max = fields.next()?.parse().ok()?;
+        let millis = fields.next()?.parse().ok()?;
+
+        if fields.next().is_some() || name.is_empty() || min > max {
+            return None;
+        }
+        Some(Score {
+            name,
+            attempts,
+            range: min..=max,
+            time: Duration::from_millis(millis),
+        })
+    }
+}
+
+/// The scores read from a file, and how many lines had to be skipped.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Scores {
+    pub scores: Vec<Score>,
+    pub skipped: usize,
+}
+
+impl Scores {
+    /// The scores, fewest attempts first and the faster one first among
+    /// equal attempts.
+    pub fn leaderboard(&self) -> Vec<&Score> {
+        let mut scores: Vec<&Score> = self.scores.iter().collect();
+        scores.sort_by_key(|score| (score.attempts, score.time));
+        scores
+    }
+}
+
+pub struct ScoreFile {
+    path: PathBuf,
+}
+
+impl ScoreFile {
+    pub fn new(path: impl AsRef<Path>) -> ScoreFile {
+        ScoreFile {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Adds `score` to the end of the file, creating the file if needed.
+    pub fn record(&self, score: &Score) -> io::Result<()> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&self.path)?;
+
+        let mut line = score.to_line();
+        // If the last line was cut short, start on a line of our own so it
+        // doesn't swallow ours.
+        if !ends_with_newline(&mut file)? {
+            line.insert(0, '\n');
+        }
+        file.write_all(line.as_bytes())
+    }
+
+    /// Reads every score. A missing file has no scores.
+    pub fn load(&self) -> io::Result<Scores> {
+        let contents = match std::fs::read(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Scores::default()),
+            Err(e) => return Err(e),
+        };
+
+        let mut scores = Scores::default();
+        let mut lines: Vec<&[u8]> = contents.split(|&b| b == b'\n').collect();
+        // Whatever follows the last newline is a line still being written, or
+        // empty.
+        if !lines.pop().unwrap_or_default().is_empty() {
+            scores.skipped += 1;
+        }
+
+        for line in lines.into_iter().filter(|line| !line.is_empty()) {
+            match std::str::from_utf8(line).ok().and_then(Score::from_line) {
+                Some(score) => scores.scores.push(score),
+                None => scores.skipped += 1,
+            }
+        }
+        Ok(scores)
+    }
+}
+
+fn ends_with_newline(file: &mut File) -> io::Result<bool> {
+    if file.metadata()?.len() == 0 {
+        return Ok(true);
+    }
+
+    let mut last = [0];
+    file.seek(SeekFrom::End(-1))?;
+    file.read_exact(&mut last)?;
+    Ok(last[0] == b'\n')
+}
+
+/// Prints the leaderboard as a table.
+pub fn write_leaderboard(scores: &Scores, mut out: impl Write) -> io::Result<()> {
+    let leaderboard = scores.leaderboard();
+    if leaderboard.is_empty() {
+        return writeln!(out, "No scores yet.");
+    }
+
+    let width = leaderboard
+        .iter()
+        .map(|score| score.name.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("Name".len());
+
+    writeln!(
+        out,
+        "{:>4}  {:<width$}  {:>8}  {:>11}  {:>8}",
+        "Rank", "Name", "Attempts", "Range", "Time"
+    )?;
+    for (rank, score) in leaderboard.iter().enumerate() {
+        writeln!(
+            out,
+            "{:>4}  {:<width$}  {:>8}  {:>11}  {:>7.1}s",
+            rank + 1,
+            score.name,
+            score.attempts,
+            format!("{}-{}", score.range.start(), score.range.end()),
+            score.time.as_secs_f64()
+        )?;
+    }
+    Ok(())
+}
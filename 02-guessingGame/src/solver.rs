@@ -0,0 +1,280 @@
+//! The game with the roles reversed: the player thinks of a number and the
+//! program guesses it.
+//!
+//! The player answers every guess with the same feedback the game gives,
+//! `Too small!` or `Too big!`, and the solver halves the numbers that are
+//! still possible each time. Once only one number is left it is named
+//! without spending a guess on it, so any number in a range of `n` numbers
+//! is found within `⌈log2(n)⌉` guesses, which [`max_guesses`] works out and
+//! [`benchmark`] checks.
+
+use std::cmp::Ordering;
+use std::io::{self, BufRead, Write};
+use std::ops::RangeInclusive;
+
+/// Binary search over the numbers the player's answers still allow.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Solver {
+    low: u32,
+    high: u32,
+    guesses: u32,
+}
+
+/// Returned when an answer rules out every number that was left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Inconsistent;
+
+impl Solver {
+    pub fn new(range: RangeInclusive<u32>) -> Solver {
+        Solver {
+            low: *range.start(),
+            high: *range.end(),
+            guesses: 0,
+        }
+    }
+
+    /// The middle of the numbers still possible.
+    pub fn guess(&self) -> u32 {
+        self.low + (self.high - self.low) / 2
+    }
+
+    /// The player's number, once the answers leave only one possibility.
+    pub fn number(&self) -> Option<u32> {
+        (self.low == self.high).then_some(self.low)
+    }
+
+    /// How many answers the solver has had.
+    pub fn guesses(&self) -> u32 {
+        self.guesses
+    }
+
+    /// Takes the player's answer to [`Solver::guess`]: how the guess compares
+    /// to their number. Returns the number if that leaves only one.
+    pub fn answer(&mut self, answer: Ordering) -> Result<Option<u32>, Inconsistent> {
+        let guess = self.guess();
+        self.guesses += 1;
+
+        match answer {
+            Ordering::Equal => (self.low, self.high) = (guess, guess),
+            Ordering::Less => self.low = guess.checked_add(1).ok_or(Inconsistent)?,
+            Ordering::Greater => self.high = guess.checked_sub(1).ok_or(Inconsistent)?,
+        }
+
+        if self.low > self.high {
+            Err(Inconsistent)
+        } else {
+            Ok(self.number())
+        }
+    }
+}
+
+/// The most guesses the solver may need for any number in `range`:
+/// `⌈log2(n)⌉` for `n` numbers.
+pub fn max_guesses(range: &RangeInclusive<u32>) -> u32 {
+    let n = u64::from(range.end() - range.start()) + 1;
+    // The number of bits in n - 1 is ⌈log2(n)⌉.
+    u64::BITS - (n - 1).leading_zeros()
+}
+
+/// How a solver game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Solved {
+        number: u32,
+        guesses: u32,
+    },
+    /// The player's answers contradicted each other.
+    Cheated,
+    /// The input ended before the number was found.
+    Quit,
+}
+
+/// Turns an answer typed by the player into how the guess compares to their
+/// number. The game's own messages work, as do short forms.
+fn parse_answer(answer: &str) -> Option<Ordering> {
+    let answer = answer.trim().trim_end_matches(['!', '.']).to_lowercase();
+
+    match answer.as_str() {
+        "too small" | "small" | "s" | "<" => Some(Ordering::Less),
+        "too big" | "big" | "b" | ">" => Some(Ordering::Greater),
+        "correct" | "yes" | "c" | "y" | "=" => Some(Ordering::Equal),
+        _ => None,
+    }
+}
+
+/// Guesses the number the player picked from `range`, asking on `output`
+/// and reading their answers from `input`.
+pub fn solve(
+    range: RangeInclusive<u32>,
+    mut input: impl BufRead,
+    mut output: impl Write,
+) -> io::Result<Outcome> {
+    writeln!(
+        output,
+        "Think of a number between {} and {}, and I'll guess it in at most {} tries.",
+        range.start(),
+        range.end(),
+        max_guesses(&range)
+    )?;
+    writeln!(
+        output,
+        "Answer each guess with \"Too small!\", \"Too big!\" or \"Correct!\" (or s, b or c)."
+    )?;
+
+    let mut solver = Solver::new(range);
+
+    loop {
+        if let Some(number) = solver.number() {
+            writeln!(
+                output,
+                "I win! Your number is {}; it took me {} guesses.",
+                number,
+                solver.guesses()
+            )?;
+            return Ok(Outcome::Solved {
+                number,
+                guesses: solver.guesses(),
+            });
+        }
+
+        let guess = solver.guess();
+        writeln!(output, "My guess: {}", guess)?;
+
+        let answer = loop {
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                return Ok(Outcome::Quit);
+            }
+
+            match parse_answer(&line) {
+                Some(answer) => break answer,
+                None => writeln!(output, "Please answer small, big or correct.")?,
+            }
+        };
+
+        if let Err(Inconsistent) = solver.answer(answer) {
+            writeln!(
+                output,
+                "Your answers contradict each other: no number fits them all. Cheater!"
+            )?;
+            return Ok(Outcome::Cheated);
+        }
+    }
+}
+
+/// The results of letting the solver guess every number in a range.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Benchmark {
+    pub games: u64,
+    /// The most guesses any number took.
+    pub worst: u32,
+    pub average: f64,
+    /// The most guesses any number may take; see [`max_guesses`].
+    pub bound: u32,
+}
+
+impl Benchmark {
+    pub fn within_bound(&self) -> bool {
+        self.worst <= self.bound
+    }
+}
+
+/// The most numbers [`benchmark`] is meant to be run on; a game for each of
+/// them takes a moment, while all of `u32` would take hours.
+pub const MAX_BENCHMARK_NUMBERS: u64 = 1_000_000;
+
+/// Plays one game against the solver for every number in `range`, answering
+/// honestly. See [`MAX_BENCHMARK_NUMBERS`] for how large a range is
+/// practical.
+pub fn benchmark(range: RangeInclusive<u32>) -> Benchmark {
+    let mut games = 0;
+    let mut worst = 0;
+    let mut total = 0;
+
+    for secret in range.clone() {
+        let mut solver = Solver::new(range.clone());
+        while solver.number().is_none() {
+            // Honest answers are always consistent.
+            solver
+                .answer(solver.guess().cmp(&secret))
+                .expect("honest answers");
+        }
+        assert_eq!(Some(secret), solver.number());
+
+        games += 1;
+        worst = worst.max(solver.guesses());
+        total += u64::from(solver.guesses());
+    }
+
+    Benchmark {
+        games,
+        worst,
+        average: total as f64 / games as f64,
+        bound: max_guesses(&range),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bound_is_the_ceiling_of_log2() {
+        assert_eq!(0, max_guesses(&(5..=5)));
+        assert_eq!(1, max_guesses(&(1..=2)));
+        assert_eq!(2, max_guesses(&(1..=3)));
+        assert_eq!(2, max_guesses(&(1..=4)));
+        assert_eq!(7, max_guesses(&(1..=100)));
+        assert_eq!(10, max_guesses(&(1..=1000)));
+        assert_eq!(32, max_guesses(&(0..=u32::MAX)));
+    }
+
+    #[test]
+    fn always_wins_within_the_bound() {
+        for end in 1..=300 {
+            let result = benchmark(1..=end);
+            assert!(result.within_bound(), "{:?} for 1..={}", result, end);
+        }
+        // For powers of two the bound is tight.
+        for power in 0..10 {
+            let result = benchmark(1..=1 << power);
+            assert_eq!(power, result.worst);
+            assert_eq!(power, result.bound);
+        }
+    }
+
+    #[test]
+    fn names_the_last_number_left_without_guessing() {
+        let mut solver = Solver::new(1..=2);
+        assert_eq!(1, solver.guess());
+        assert_eq!(Ok(Some(2)), solver.answer(Ordering::Less));
+        assert_eq!(1, solver.guesses());
+
+        assert_eq!(Some(5), Solver::new(5..=5).number());
+    }
+
+    #[test]
+    fn handles_the_edges_of_u32() {
+        let mut solver = Solver::new(0..=u32::MAX);
+        while solver.number().is_none() {
+            solver.answer(Ordering::Less).unwrap();
+        }
+        assert_eq!(Some(u32::MAX), solver.number());
+        assert_eq!(32, solver.guesses());
+
+        let mut solver = Solver::new(0..=u32::MAX);
+        while solver.number().is_none() {
+            solver.answer(Ordering::Greater).unwrap();
+        }
+        assert_eq!(Some(0), solver.number());
+        assert_eq!(Err(Inconsistent), solver.answer(Ordering::Greater));
+    }
+
+    #[test]
+    fn reads_answers() {
+        assert_eq!(Some(Ordering::Less), parse_answer("Too small!\n"));
+        assert_eq!(Some(Ordering::Greater), parse_answer(" BIG "));
+        assert_eq!(Some(Ordering::Equal), parse_answer("c"));
+        assert_eq!(None, parse_answer("maybe"));
+    }
+}
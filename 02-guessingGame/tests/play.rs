@@ -0,0 +1,230 @@
+//! Plays whole games from a script of guesses (or, against the solver, of
+//! answers) and checks the transcript.
+//!
+//! The secret comes from a seeded generator, so each test works it out from
+//! the same seed first and writes its guesses around it.
+
+use std::process::{Command, Stdio};
+use std::{io::Write, ops::RangeInclusive};
+
+use guessing_game::{play, solver, Outcome, Settings};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+const SEED: u64 = 42;
+
+fn secret(range: RangeInclusive<u32>) -> u32 {
+    StdRng::seed_from_u64(SEED).gen_range(range)
+}
+
+/// Plays a game with `guesses` as the input, returning how it ended and
+/// what the game printed.
+fn run(settings: &Settings, guesses: &[String]) -> (Outcome, String) {
+    let input: String = guesses.iter().map(|guess| format!("{}\n", guess)).collect();
+    let mut output = Vec::new();
+
+    let outcome = play(
+        settings,
+        &mut StdRng::seed_from_u64(SEED),
+        input.as_bytes(),
+        &mut output,
+    )
+    .unwrap();
+    (outcome, String::from_utf8(output).unwrap())
+}
+
+#[test]
+fn winning_game() {
+    let settings = Settings::new(1..=100, 7);
+    let secret = secret(1..=100);
+    // One guess on each side of the secret, within the range.
+    let (low, high) = (secret.max(2) - 1, secret.min(99) + 1);
+
+    let guesses = vec![
+        String::from("banana"),
+        String::from("0"),
+        low.to_string(),
+        high.to_string(),
+        secret.to_string(),
+    ];
+    let (outcome, transcript) = run(&settings, &guesses);
+
+    let mut expected = String::from(
+        "Guess the number!
+It is between 1 and 100, and you have 7 attempts.
+Please input your guess.
+Please input your guess.
+The secret number is between 1 and 100.
+Please input your guess.
+",
+    );
+    let mut attempts = 0;
+    for guess in [low, high] {
+        if guess == secret {
+            continue;
+        }
+        attempts += 1;
+        let hint = if guess < secret {
+            "Too small!"
+        } else {
+            "Too big!"
+        };
+        expected += &format!(
+            "You guessed: {}\n{}\nAttempts left: {}\nPlease input your guess.\n",
+            guess,
+            hint,
+            7 - attempts
+        );
+    }
+    expected += &format!("You guessed: {}\nYou win!\n", secret);
+
+    assert_eq!(expected, transcript);
+    assert_eq!(
+        Outcome::Won {
+            attempts: attempts + 1
+        },
+        outcome
+    );
+}
+
+#[test]
+fn losing_game() {
+    let settings = Settings::new(1..=2, 1);
+    let secret = secret(1..=2);
+    let wrong = 3 - secret;
+
+    let (outcome, transcript) = run(&settings, &[wrong.to_string(), secret.to_string()]);
+
+    let hint = if wrong < secret {
+        "Too small!"
+    } else {
+        "Too big!"
+    };
+    assert_eq!(
+        format!(
+            "Guess the number!
+It is between 1 and 2, and you have 1 attempts.
+Please input your guess.
+You guessed: {}
+{}
+You lose! The secret number was {}.
+",
+            wrong, hint, secret
+        ),
+        transcript
+    );
+    assert_eq!(Outcome::Lost { secret }, outcome);
+}
+
+#[test]
+fn input_ending_early() {
+    let settings = Settings::new(5..=5, 3);
+
+    let (outcome, transcript) = run(&settings, &[]);
+
+    assert_eq!(
+        "Guess the number!
+It is between 5 and 5, and you have 3 attempts.
+Please input your guess.
+The secret number was 5.
+",
+        transcript
+    );
+    assert_eq!(Outcome::Quit { secret: 5 }, outcome);
+}
+
+#[test]
+fn binary_with_seed() {
+    let secret = secret(1..=1000);
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_guessing_game"))
+        .args(["--difficulty", "hard", "--seed", &SEED.to_string()])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    writeln!(child.stdin.take().unwrap(), "{}", secret).unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        format!(
+            "Guess the number!
+It is between 1 and 1000, and you have 10 attempts.
+Please input your guess.
+You guessed: {}
+You win!
+",
+            secret
+        ),
+        String::from_utf8(output.stdout).unwrap()
+    );
+}
+
+#[test]
+fn solver_game() {
+    let mut output = Vec::new();
+    // The player thinks of 68.
+    let answers = "Too small!\nwhat?\nbig\ns\nc\n";
+
+    let outcome = solver::solve(1..=100, answers.as_bytes(), &mut output).unwrap();
+
+    assert_eq!(
+        "\
+Think of a number between 1 and 100, and I'll guess it in at most 7 tries.
+Answer each guess with \"Too small!\", \"Too big!\" or \"Correct!\" (or s, b or c).
+My guess: 50
+My guess: 75
+Please answer small, big or correct.
+My guess: 62
+My guess: 68
+I win! Your number is 68; it took me 4 guesses.
+",
+        String::from_utf8(output).unwrap()
+    );
+    assert_eq!(
+        solver::Outcome::Solved {
+            number: 68,
+            guesses: 4
+        },
+        outcome
+    );
+}
+
+#[test]
+fn solver_catches_cheating() {
+    let mut output = Vec::new();
+
+    // Bigger than 2 but smaller than 3.
+    let outcome = solver::solve(1..=4, "small\nbig\n".as_bytes(), &mut output).unwrap();
+
+    assert_eq!(solver::Outcome::Cheated, outcome);
+    assert!(String::from_utf8(output)
+        .unwrap()
+        .ends_with("My guess: 2\nMy guess: 3\nYour answers contradict each other: no number fits them all. Cheater!\n"));
+}
+
+#[test]
+fn benchmark_command() {
+    let output = Command::new(env!("CARGO_BIN_EXE_guessing_game"))
+        .args(["--benchmark", "--difficulty", "hard"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout).unwrap().starts_with(
+        "Solved all 1000 numbers from 1 to 1000: at most 9 guesses (8.50 on average), bound 10."
+    ));
+}
+
+#[test]
+fn benchmark_refuses_huge_ranges() {
+    let output = Command::new(env!("CARGO_BIN_EXE_guessing_game"))
+        .args(["--benchmark", "--min", "0", "--max", "4294967295"])
+        .output()
+        .unwrap();
+
+    assert_eq!(Some(2), output.status.code());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("may hold at most 1000000 numbers, not 4294967296"));
+}
@@ -0,0 +1,153 @@
+//! The high-score file, including files that were cut short or written by
+//! several games at once.
+
+use std::{fs, process::Command, sync::Arc, thread, time::Duration};
+
+use clap::Parser;
+use guessing_game::{
+    cli::Cli,
+    scores::{write_leaderboard, Score, ScoreFile},
+};
+
+fn score(name: &str, attempts: u32, millis: u64) -> Score {
+    Score {
+        name: name.to_string(),
+        attempts,
+        range: 1..=100,
+        time: Duration::from_millis(millis),
+    }
+}
+
+#[test]
+fn ranks_by_attempts_then_time() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = ScoreFile::new(dir.path().join("scores.tsv"));
+    assert!(file.load().unwrap().scores.is_empty());
+
+    file.record(&score("slow", 3, 9000)).unwrap();
+    file.record(&score("lucky", 1, 20000)).unwrap();
+    file.record(&score("fast", 3, 1500)).unwrap();
+    file.record(&score("tab\tby", 5, 0)).unwrap();
+
+    let scores = file.load().unwrap();
+    let names: Vec<&str> = scores
+        .leaderboard()
+        .iter()
+        .map(|score| score.name.as_str())
+        .collect();
+    assert_eq!(vec!["lucky", "fast", "slow", "tab by"], names);
+    assert_eq!(0, scores.skipped);
+
+    let mut out = Vec::new();
+    write_leaderboard(&scores, &mut out).unwrap();
+    assert_eq!(
+        "\
+Rank  Name    Attempts        Range      Time
+   1  lucky          1        1-100     20.0s
+   2  fast           3        1-100      1.5s
+   3  slow           3        1-100      9.0s
+   4  tab by         5        1-100      0.0s
+",
+        String::from_utf8(out).unwrap()
+    );
+}
+
+#[test]
+fn names_are_cleaned_before_they_are_recorded() {
+    let player = |name: &str| {
+        Cli::try_parse_from(["guessing_game", "--name", name])
+            .unwrap()
+            .player()
+    };
+
+    assert_eq!("tab by", player(" tab\tby\n"));
+    assert_eq!("anonymous", player("\u{1}"));
+    assert_eq!("anonymous", player("\u{1}\t \u{7f}"));
+
+    let dir = tempfile::tempdir().unwrap();
+    let file = ScoreFile::new(dir.path().join("scores.tsv"));
+    file.record(&score(&player("\u{1}"), 4, 1000)).unwrap();
+
+    let scores = file.load().unwrap();
+    assert_eq!(vec![score("anonymous", 4, 1000)], scores.scores);
+    assert_eq!(0, scores.skipped);
+}
+
+#[test]
+fn skips_lines_it_cannot_read() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("scores.tsv");
+    fs::write(
+        &path,
+        "alice\t2\t1\t100\t3000\n\
+         garbage\n\
+         bob\ttwo\t1\t100\t3000\n\
+         \u{0}\u{ff}\n\
+         carol\t4\t1\t10",
+    )
+    .unwrap();
+    let file = ScoreFile::new(&path);
+
+    let scores = file.load().unwrap();
+    assert_eq!(vec![score("alice", 2, 3000)], scores.scores);
+    assert_eq!(4, scores.skipped);
+
+    // The torn line at the end doesn't swallow the next score.
+    file.record(&score("dave", 1, 10)).unwrap();
+    let scores = file.load().unwrap();
+    assert_eq!(
+        vec![score("alice", 2, 3000), score("dave", 1, 10)],
+        scores.scores
+    );
+}
+
+#[test]
+fn games_can_record_at_the_same_time() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = Arc::new(ScoreFile::new(dir.path().join("scores.tsv")));
+
+    let handles: Vec<_> = (0..8)
+        .map(|i| {
+            let file = Arc::clone(&file);
+            thread::spawn(move || {
+                for j in 0..50 {
+                    file.record(&score(&format!("player {}", i), j, 100))
+                        .unwrap();
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let scores = file.load().unwrap();
+    assert_eq!(400, scores.scores.len());
+    assert_eq!(0, scores.skipped);
+}
+
+#[test]
+fn scores_command() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("scores.tsv");
+    fs::write(&path, "alice\t2\t1\t100\t3000\nbroken\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_guessing_game"))
+        .arg("--scores")
+        .arg("--score-file")
+        .arg(&path)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        "\
+Rank  Name   Attempts        Range      Time
+   1  alice         2        1-100      3.0s
+",
+        String::from_utf8(output.stdout).unwrap()
+    );
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .starts_with("Skipped 1 unreadable lines"));
+}
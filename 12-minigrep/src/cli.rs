@@ -0,0 +1,136 @@
+//! Command-line interface definition.
+//!
+//! Everything the parser accepts is declared once on [`Cli`]; the shell
+//! completions and the man page are generated from the same definition so
+//! they can't drift from it:
+//!
+//! ```text
+//! minigrep completions bash > ~/.local/share/bash-completion/completions/minigrep
+//! minigrep completions zsh > ~/.zfunc/_minigrep
+//! minigrep completions fish > ~/.config/fish/completions/minigrep.fish
+//! minigrep man > ~/.local/share/man/man1/minigrep.1
+//! ```
+
+use std::io::{self, Write};
+
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "minigrep",
+    version,
+    about = "Search files for lines containing a query",
+    after_help = "To search for a query that is also a subcommand name, put it after `--`: \
+                  minigrep -- man notes.txt",
+    args_conflicts_with_subcommands = true,
+    disable_help_subcommand = true,
+    subcommand_negates_reqs = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Text to look for
+    #[arg(required = true)]
+    pub query: Option<String>,
+
+    /// Files or directories to search; directories are searched recursively
+    #[arg(value_name = "PATH", required = true)]
+    pub paths: Vec<String>,
+
+    /// Ignore case when matching (also enabled by setting CASE_INSENSITIVE)
+    #[arg(short, long)]
+    pub ignore_case: bool,
+
+    /// Browse the results in an interactive terminal UI
+    #[arg(long)]
+    pub tui: bool,
+
+    /// Let matches span lines: whitespace in the query matches any whitespace,
+    /// including line breaks
+    #[arg(short = 'U', long, conflicts_with = "tui")]
+    pub multiline: bool,
+
+    /// Print statistics and per-phase timings to stderr after searching
+    #[arg(long, conflicts_with = "tui")]
+    pub stats: bool,
+}
+
+#[derive(Subcommand, Debug, PartialEq)]
+pub enum Command {
+    /// Print a completion script for the given shell
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// Print the man page in roff format
+    Man,
+}
+
+/// Writes the output of a generator subcommand to `out`.
+pub fn generate(command: Command, out: &mut dyn Write) -> io::Result<()> {
+    match command {
+        Command::Completions { shell } => {
+            let mut cmd = Cli::command();
+            clap_complete::generate(shell, &mut cmd, "minigrep", out);
+            Ok(())
+        }
+        Command::Man => clap_mangen::Man::new(Cli::command()).render(out),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generated(command: Command) -> String {
+        let mut out = Vec::new();
+        generate(command, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn definition_is_valid() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn parses_subcommands() {
+        let cli = Cli::try_parse_from(["minigrep", "completions", "zsh"]).unwrap();
+        assert_eq!(
+            Some(Command::Completions { shell: Shell::Zsh }),
+            cli.command
+        );
+
+        let cli = Cli::try_parse_from(["minigrep", "man"]).unwrap();
+        assert_eq!(Some(Command::Man), cli.command);
+    }
+
+    #[test]
+    fn query_may_shadow_subcommand_after_separator() {
+        let cli = Cli::try_parse_from(["minigrep", "--", "man", "notes.txt"]).unwrap();
+
+        assert_eq!(None, cli.command);
+        assert_eq!(Some("man"), cli.query.as_deref());
+    }
+
+    #[test]
+    fn completions_know_every_option() {
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish] {
+            let script = generated(Command::Completions { shell });
+
+            assert!(script.contains("ignore-case"), "{:?}", shell);
+            assert!(script.contains("tui"), "{:?}", shell);
+        }
+    }
+
+    #[test]
+    fn man_page_documents_options() {
+        let page = generated(Command::Man);
+
+        assert!(page.starts_with(".ie"), "{}", page);
+        assert!(page.contains(".TH minigrep 1"));
+        assert!(page.contains("ignore\\-case"));
+    }
+}
@@ -1,12 +1,29 @@
-use std::{env, error::Error, fs};
+use std::{
+    env,
+    error::Error,
+    fs, io,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+pub mod cli;
+pub mod stats;
+pub mod tui;
+
+use cli::Cli;
+use stats::Stats;
+
 pub struct Config {
     pub query: String,
-    pub filename: String,
+    pub filenames: Vec<String>,
     pub case_sensitive: bool,
+    pub interactive: bool,
+    pub multiline: bool,
+    pub stats: bool,
 }
 
-impl Config {
-    pub fn new(mut args: env::Args) -> Result<Config, &'static str> {
+impl From<Cli> for Config {
+    fn from(cli: Cli) -> Config {
         // if args.len() < 3 {
         //     return Err("not enough arguments");
 
@@ -17,41 +34,39 @@ impl Config {
         // let query = args[1].clone();
         // let filename = args[2].clone();
 
-        args.next();
-
-        let query = match args.next() {
-            Some(arg) => arg,
-            None => return Err("Didn't get a query string"),
-        };
-
-        let filename = match args.next() {
-            Some(arg) => arg,
-            None => return Err("Didn't get a file name"),
-        };
+        // ! clap now checks that a query and at least one path were given and
+        // ! reports the error itself, so building a Config can't fail anymore.
 
-        // e don’t care about the value of the environment variable, just
-        // whether it’s set or unset, so we’re checking is_err rather than using
-        // unwrap, expect
+        // The parser only leaves the query empty when a subcommand was given,
+        // and subcommands never reach a search.
+        let query = cli.query.unwrap_or_default();
+        let filenames = cli.paths;
 
-        // call the clone method on the values.
-        // This will make a full copy of the
+        // The first version called the clone method on the values.
+        // This made a full copy of the
         // data for the Config instance to own, which takes more time and memory
         // than storing a reference to the string data. However, cloning the data
         // also makes our code very straightforward because we don’t have to manage
         // the lifetimes of the references; in this circumstance, giving up a little
         // performance to gain simplicity is a worthwhile trade-off.
 
-        // ! As you become more experienced with Rust, it’ll be easier to start
-        // ! with the most efficient solution, but for now, it’s perfectly acceptable
-        // ! to call clone.
+        // ! Taking `cli` by value gets us the best of both: Config still owns its
+        // ! strings, but they are moved out of the parsed arguments instead of
+        // ! copied, so there is nothing left to clone.
 
-        let case_sensitive = env::var("CASE_INSENSITIVE").is_err();
+        // We don’t care about the value of the environment variable, just
+        // whether it’s set or unset, so we’re checking is_err rather than using
+        // unwrap, expect
+        let case_sensitive = !cli.ignore_case && env::var("CASE_INSENSITIVE").is_err();
 
-        Ok(Config {
+        Config {
             query,
-            filename,
+            filenames,
             case_sensitive,
-        })
+            interactive: cli.tui,
+            multiline: cli.multiline,
+            stats: cli.stats,
+        }
     }
 }
 
@@ -65,24 +80,315 @@ pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
     // $  ? will return the error value from the current function for the caller
     // $ to handle.
 
-    let contents = fs::read_to_string(config.filename)?;
+    let mut stats = Stats::default();
+    let started = Instant::now();
+
+    let sources = read_sources(&config.filenames, &mut stats)?;
+    stats.read_time = started.elapsed();
+
+    if config.interactive {
+        return tui::browse(&config, &sources);
+    }
+
+    // Like grep, only prefix results with their file name when more than one
+    // file could have produced them.
+    let show_names = config.filenames.len() > 1 || !Path::new(&config.filenames[0]).is_file();
+
+    for source in &sources {
+        let prefix = if show_names {
+            format!("{}:", source.path.display())
+        } else {
+            String::new()
+        };
+
+        let started = Instant::now();
+
+        if config.multiline {
+            let results = search_multiline(&config.query, &source.contents, config.case_sensitive);
+            stats.record_search(source, results.len(), started.elapsed());
+
+            let started = Instant::now();
+            print_multiline(&results, &prefix);
+            stats.output_time += started.elapsed();
+        } else {
+            let results = if config.case_sensitive {
+                search(&config.query, &source.contents)
+            } else {
+                search_case_insensitive(&config.query, &source.contents)
+            };
+            stats.record_search(source, results.len(), started.elapsed());
+
+            let started = Instant::now();
+            for line in results {
+                println!("{}{}", prefix, line);
+            }
+            stats.output_time += started.elapsed();
+        }
+    }
+
+    if config.stats {
+        // Statistics go to stderr so they don't mix with results that are
+        // piped into another program.
+        eprint!("{}", stats);
+    }
+
+    Ok(())
+}
+
+/// Prints every multiline match as a `start-end:` line range (or a single
+/// line number) followed by the full lines the match covers.
+fn print_multiline(results: &[MultilineMatch], prefix: &str) {
+    for m in results {
+        if m.start_line == m.end_line {
+            println!("{}{}:", prefix, m.start_line);
+        } else {
+            println!("{}{}-{}:", prefix, m.start_line, m.end_line);
+        }
+
+        for line in m.lines.lines() {
+            println!("{}", line);
+        }
+    }
+}
+
+/// A file that has been read into memory, ready to be searched.
+pub struct SourceFile {
+    pub path: PathBuf,
+    pub contents: String,
+}
+
+/// Reads every file named in `filenames`, descending into directories.
+///
+/// Files found inside a directory are visited in sorted order and skipped if
+/// they are not valid UTF-8, so that binary files don't abort the search.
+/// Files named explicitly must be readable text. Every byte read is counted in
+/// `stats`, skipped files included.
+pub fn read_sources(
+    filenames: &[String],
+    stats: &mut Stats,
+) -> Result<Vec<SourceFile>, Box<dyn Error>> {
+    let mut sources = Vec::new();
+
+    for filename in filenames {
+        let path = PathBuf::from(filename);
+
+        if path.is_dir() {
+            let mut paths = Vec::new();
+            collect_files(&path, &mut paths)?;
+
+            for path in paths {
+                let bytes = fs::read(&path)?;
+                stats.bytes_read += bytes.len();
+
+                if let Ok(contents) = String::from_utf8(bytes) {
+                    sources.push(SourceFile { path, contents });
+                }
+            }
+        } else {
+            let contents = fs::read_to_string(&path)?;
+            stats.bytes_read += contents.len();
+            sources.push(SourceFile { path, contents });
+        }
+    }
+
+    Ok(sources)
+}
+
+fn collect_files(dir: &Path, paths: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            collect_files(&path, paths)?;
+        } else {
+            paths.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn search<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
+    contents
+        .lines()
+        .filter(|line| line.contains(query))
+        .collect()
+}
+
+pub fn search_case_insensitive<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
+    // let query = query.to_lowercase();
+    // let mut results = Vec::new();
+
+    // for line in contents.lines() {
+    //     if line.to_lowercase().contains(&query) {
+    //         results.push(line);
+    //     }
+    // }
+
+    // results
+
+    let query = query.to_lowercase();
 
-    let results = if config.case_sensitive {
-        search(&config.query, &contents)
+    contents
+        // .to_lowercase()
+        .lines()
+        .filter(|line| line.to_lowercase().contains(&query))
+        .collect()
+}
+
+/// Like [`search`], but keeps the 1-based line number of every match so that
+/// callers can point back into the file.
+pub fn search_with_line_numbers<'a>(
+    query: &str,
+    contents: &'a str,
+    case_sensitive: bool,
+) -> Vec<(usize, &'a str)> {
+    let query = if case_sensitive {
+        query.to_string()
     } else {
-        search_case_insensitive(&config.query, &contents)
+        query.to_lowercase()
     };
 
-    for line in results {
-        println!("{}", line);
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            if case_sensitive {
+                line.contains(&query)
+            } else {
+                line.to_lowercase().contains(&query)
+            }
+        })
+        .map(|(i, line)| (i + 1, line))
+        .collect()
+}
+
+/// A match found by [`search_multiline`], which may span several lines.
+#[derive(Debug, PartialEq)]
+pub struct MultilineMatch<'a> {
+    /// The exact text that matched.
+    pub text: &'a str,
+    /// Every line the match touches, from the start of its first line to the
+    /// end of its last.
+    pub lines: &'a str,
+    /// 1-based number of the line the match starts on.
+    pub start_line: usize,
+    /// 1-based number of the line the match ends on.
+    pub end_line: usize,
+}
+
+/// Searches `contents` as a whole instead of line by line, so that a match
+/// can cross line boundaries.
+///
+/// Whitespace in `contents`, newlines included, may appear next to any
+/// punctuation in the query, and a run of whitespace in the query matches any
+/// run of whitespace in `contents`. Only the inside of a word has to match
+/// exactly. That way `fn search(query: &str, contents: &str)` also finds a
+/// signature whose parameters have been wrapped onto lines of their own.
+/// Matches don't overlap; a query without any words matches nothing.
+pub fn search_multiline<'a>(
+    query: &str,
+    contents: &'a str,
+    case_sensitive: bool,
+) -> Vec<MultilineMatch<'a>> {
+    let query = query.trim();
+    let mut results = Vec::new();
+
+    if query.is_empty() {
+        return results;
     }
 
-    Ok(())
+    let mut start = 0;
+
+    while let Some(c) = contents[start..].chars().next() {
+        match match_query(query, &contents[start..], case_sensitive) {
+            Some(len) => {
+                results.push(multiline_match(contents, start, start + len));
+                start += len;
+            }
+            None => start += c.len_utf8(),
+        }
+    }
+
+    results
+}
+
+/// Returns the length in bytes of the match of `query` at the very start of
+/// `text`, if there is one. `query` must not start with whitespace.
+fn match_query(query: &str, text: &str, case_sensitive: bool) -> Option<usize> {
+    let mut len = 0;
+    // The last character of the query that wasn't whitespace, and whether
+    // whitespace came after it.
+    let mut previous: Option<char> = None;
+    let mut gap = false;
+
+    for q in query.chars() {
+        if q.is_whitespace() {
+            gap = true;
+            continue;
+        }
+
+        if let Some(p) = previous {
+            let space: usize = text[len..]
+                .chars()
+                .take_while(|c| c.is_whitespace())
+                .map(char::len_utf8)
+                .sum();
+
+            // Between two word characters, whitespace is there exactly when
+            // the query has it. Next to punctuation it is up to the file, so
+            // `(query` matches `(\n    query` and `a, b` matches `a,b`.
+            if is_word(p) && is_word(q) && gap != (space > 0) {
+                return None;
+            }
+            len += space;
+        }
+
+        let c = text[len..].chars().next()?;
+        let same = if case_sensitive {
+            c == q
+        } else {
+            c.to_lowercase().eq(q.to_lowercase())
+        };
+
+        if !same {
+            return None;
+        }
+        len += c.len_utf8();
+
+        previous = Some(q);
+        gap = false;
+    }
+
+    Some(len)
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn multiline_match(contents: &str, start: usize, end: usize) -> MultilineMatch<'_> {
+    let line_start = contents[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = contents[end..]
+        .find('\n')
+        .map_or(contents.len(), |i| end + i);
+
+    MultilineMatch {
+        text: &contents[start..end],
+        lines: contents[line_start..line_end].trim_end_matches('\r'),
+        start_line: contents[..start].matches('\n').count() + 1,
+        // A match can't end with a newline: it always ends inside a word.
+        end_line: contents[..end].matches('\n').count() + 1,
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use clap::Parser;
 
     #[test]
     fn one_result() {
@@ -123,89 +429,120 @@ Trust me.";
     }
 
     #[test]
-    fn iterator() {
-        let v1: Vec<i32> = vec![1, 2, 3];
-
-        let v2: Vec<_> = v1.iter().map(|x| x + 1).collect();
+    fn line_numbers() {
+        let contents = "\
+Rust:
+safe, fast, productive.
+Pick three.
+Trust me.";
 
-        assert_eq!(v2, vec![2, 3, 4]);
+        assert_eq!(
+            vec![(1, "Rust:"), (4, "Trust me.")],
+            search_with_line_numbers("rUsT", contents, false)
+        );
+        assert_eq!(
+            vec![(4, "Trust me.")],
+            search_with_line_numbers("rust", contents, true)
+        );
     }
 
     #[test]
-    fn filters_by_size() {
-        let shoes = vec![
-            Shoe {
-                size: 10,
-                style: String::from("sneaker"),
-            },
-            Shoe {
-                size: 13,
-                style: String::from("sandal"),
-            },
-            Shoe {
-                size: 10,
-                style: String::from("boot"),
-            },
-        ];
-
-        let in_my_size = shoes_in_size(shoes, 10);
+    fn parses_options_and_paths() {
+        let cli = Cli::try_parse_from(["minigrep", "--tui", "-i", "to", "poem.txt", "src"]);
+        let config = Config::from(cli.unwrap());
+
+        assert_eq!("to", config.query);
+        assert_eq!(vec!["poem.txt", "src"], config.filenames);
+        assert!(config.interactive);
+        assert!(!config.case_sensitive);
+    }
 
-        assert_eq!(
-            in_my_size,
-            vec![
-                Shoe {
-                    size: 10,
-                    style: String::from("sneaker")
-                },
-                Shoe {
-                    size: 10,
-                    style: String::from("boot")
-                },
-            ]
-        );
+    #[test]
+    fn rejects_unknown_option() {
+        assert!(Cli::try_parse_from(["minigrep", "--nope", "to", "poem.txt"]).is_err());
     }
-}
 
-pub fn search<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
-    contents
-        .lines()
-        .filter(|line| line.contains(query))
-        .collect()
-}
+    #[test]
+    fn multiline_spans_lines() {
+        let contents = "\
+pub fn search<'a>(
+    query: &str,
+    contents: &'a str,
+) -> Vec<&'a str> {";
 
-pub fn search_case_insensitive<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
-    // let query = query.to_lowercase();
-    // let mut results = Vec::new();
+        let results = search_multiline("search<'a>( query: &str, contents", contents, true);
 
-    // for line in contents.lines() {
-    //     if line.to_lowercase().contains(&query) {
-    //         results.push(line);
-    //     }
-    // }
-
-    // results
+        assert_eq!(1, results.len());
+        assert_eq!(1, results[0].start_line);
+        assert_eq!(3, results[0].end_line);
+        assert_eq!(
+            "search<'a>(\n    query: &str,\n    contents",
+            results[0].text
+        );
+        assert_eq!(
+            "pub fn search<'a>(\n    query: &str,\n    contents: &'a str,",
+            results[0].lines
+        );
+    }
 
-    contents
-        // .to_lowercase()
-        .lines()
-        .filter(|line| line.to_lowercase().contains(query))
-        .collect()
-}
+    #[test]
+    fn multiline_within_one_line() {
+        let contents = "\
+Rust:
+safe, fast, productive.
+Pick three.
+Trust me.";
 
-#[derive(PartialEq, Debug)]
-struct Shoe {
-    size: u32,
-    style: String,
-}
+        let results = search_multiline("RUST", contents, false);
 
-fn shoes_in_size(shoes: Vec<Shoe>, shoe_size: u32) -> Vec<Shoe> {
-    shoes.into_iter().filter(|s| s.size == shoe_size).collect()
-}
+        assert_eq!(
+            vec![(1, 1, "Rust:"), (4, 4, "Trust me.")],
+            results
+                .iter()
+                .map(|m| (m.start_line, m.end_line, m.lines))
+                .collect::<Vec<_>>()
+        );
+        assert!(search_multiline("RUST", contents, true).is_empty());
+        assert!(search_multiline("  ", contents, true).is_empty());
+    }
 
-fn wow() {
-    let v1: Vec<i32> = vec![1, 2, 3];
+    #[test]
+    fn multiline_finds_wrapped_signatures() {
+        let contents = "\
+/// Finds the lines.
+pub fn search<'a>(
+    query: &str,
+    contents: &'a str
+) -> Vec<&'a str> {";
+
+        let results = search_multiline(
+            "fn search<'a>(query: &str, contents: &'a str) -> Vec<&'a str>",
+            contents,
+            true,
+        );
 
-    let v2: Vec<_> = v1.iter().map(|x| x + 1).collect();
+        assert_eq!(1, results.len());
+        assert_eq!(2, results[0].start_line);
+        assert_eq!(5, results[0].end_line);
+        assert_eq!(
+            "fn search<'a>(\n    query: &str,\n    contents: &'a str\n) -> Vec<&'a str>",
+            results[0].text
+        );
+        assert_eq!(
+            "pub fn search<'a>(\n    query: &str,\n    contents: &'a str\n) -> Vec<&'a str> {",
+            results[0].lines
+        );
+    }
 
-    assert_eq!(v2, vec![2, 3, 4]);
+    #[test]
+    fn multiline_requires_whitespace_between_words() {
+        assert!(search_multiline("safe fast", "safe,\nfast", true).is_empty());
+        assert!(search_multiline("safe fast", "safefast", true).is_empty());
+        assert!(search_multiline("safefast", "safe fast", true).is_empty());
+        assert_eq!(1, search_multiline("safe,fast", "safe, fast", true).len());
+        assert_eq!(
+            1,
+            search_multiline("safe, fast", "safe,\n\tfast", true).len()
+        );
+    }
 }
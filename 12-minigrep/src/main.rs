@@ -1,22 +1,32 @@
-use minigrep::Config;
-use std::{env, process};
+use clap::Parser;
+use minigrep::{cli::Cli, Config};
+use std::{io, process};
 fn main() {
-    // Using unwrap_or_else allows us to define some custom, non-panic! error
-    // handling
-    let config = Config::new(env::args()).unwrap_or_else(|err| {
-        // ! The standard library provides the eprintln! macro that prints to
-        // ! the standard error stream
-        eprintln!("Problem parsing arguments: {}", err);
+    // clap prints usage errors, --help and --version itself and exits with the
+    // appropriate status, so there is nothing left for us to handle here.
+    let cli = Cli::parse();
 
-        // A nonzero exit status is a convention to signal to the process that
-        // called our program that the program exited with an error state.
-        process::exit(1);
-    });
+    if let Some(command) = cli.command {
+        if let Err(e) = minigrep::cli::generate(command, &mut io::stdout()) {
+            // ! The standard library provides the eprintln! macro that prints to
+            // ! the standard error stream
+            eprintln!("Application error: {}", e);
+
+            // A nonzero exit status is a convention to signal to the process that
+            // called our program that the program exited with an error state.
+            process::exit(1);
+        }
+        return;
+    }
+
+    // Unlike Config::new, which returned a Result we had to handle with
+    // unwrap_or_else, converting the parsed arguments can't fail.
+    let config = Config::from(cli);
 
     // we prefix the run function with our crate name
     if let Err(e) = minigrep::run(config) {
         // the run function doesn’t return a value that we want to unwrap in the
-        // same way that Config::new returns the Config instance.
+        // same way that Config::from returns the Config instance.
 
         // Because run returns () in the success case, we only care about
         // detecting an error, so we don’t need unwrap_or_else to return the
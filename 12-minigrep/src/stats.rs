@@ -0,0 +1,110 @@
+//! Counters and timings collected while searching, printed by `--stats`.
+
+use std::{fmt, time::Duration};
+
+use crate::SourceFile;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Stats {
+    pub files_scanned: usize,
+    pub files_with_matches: usize,
+    /// Bytes of every file read, including the ones skipped because they
+    /// aren't text.
+    pub bytes_read: usize,
+    pub lines_scanned: usize,
+    pub matches: usize,
+    /// Time spent finding and reading the files.
+    pub read_time: Duration,
+    /// Time spent searching the contents of the files.
+    pub search_time: Duration,
+    /// Time spent printing the results.
+    pub output_time: Duration,
+}
+
+impl Stats {
+    /// Accounts for one searched file that produced `matches` results.
+    pub fn record_search(&mut self, source: &SourceFile, matches: usize, elapsed: Duration) {
+        self.files_scanned += 1;
+        self.lines_scanned += source.contents.lines().count();
+        self.matches += matches;
+        self.search_time += elapsed;
+
+        if matches > 0 {
+            self.files_with_matches += 1;
+        }
+    }
+
+    pub fn total_time(&self) -> Duration {
+        self.read_time + self.search_time + self.output_time
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "files scanned:      {}", self.files_scanned)?;
+        writeln!(f, "files with matches: {}", self.files_with_matches)?;
+        writeln!(f, "bytes read:         {}", self.bytes_read)?;
+        writeln!(f, "lines scanned:      {}", self.lines_scanned)?;
+        writeln!(f, "matches found:      {}", self.matches)?;
+        writeln!(f, "read time:          {:.3?}", self.read_time)?;
+        writeln!(f, "search time:        {:.3?}", self.search_time)?;
+        writeln!(f, "output time:        {:.3?}", self.output_time)?;
+        writeln!(f, "total time:         {:.3?}", self.total_time())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn source(contents: &str) -> SourceFile {
+        SourceFile {
+            path: PathBuf::from("test.txt"),
+            contents: String::from(contents),
+        }
+    }
+
+    #[test]
+    fn records_searches() {
+        let mut stats = Stats::default();
+
+        stats.record_search(&source("one\ntwo\n"), 2, Duration::from_millis(2));
+        stats.record_search(&source("three"), 0, Duration::from_millis(1));
+
+        assert_eq!(2, stats.files_scanned);
+        assert_eq!(1, stats.files_with_matches);
+        assert_eq!(3, stats.lines_scanned);
+        assert_eq!(2, stats.matches);
+        assert_eq!(Duration::from_millis(3), stats.search_time);
+    }
+
+    #[test]
+    fn report() {
+        let stats = Stats {
+            files_scanned: 2,
+            files_with_matches: 1,
+            bytes_read: 13,
+            lines_scanned: 3,
+            matches: 2,
+            read_time: Duration::from_micros(1500),
+            search_time: Duration::from_millis(3),
+            output_time: Duration::from_micros(250),
+        };
+
+        assert_eq!(
+            "\
+files scanned:      2
+files with matches: 1
+bytes read:         13
+lines scanned:      3
+matches found:      2
+read time:          1.500ms
+search time:        3.000ms
+output time:        250.000µs
+total time:         4.750ms
+",
+            stats.to_string()
+        );
+    }
+}
@@ -0,0 +1,524 @@
+//! Interactive terminal browser for search results.
+//!
+//! Matches are listed grouped by file underneath an editable query line. The
+//! query is re-run against the already loaded files on every keystroke, the
+//! lines around the selected match are previewed at the bottom of the screen,
+//! and Enter opens the selected match in `$EDITOR` at the matching line.
+
+use std::{
+    env,
+    error::Error,
+    io::{self, Write},
+    path::Path,
+    process::Command,
+};
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::{Attribute, Print, SetAttribute},
+    terminal::{self, ClearType},
+};
+
+use crate::{search_with_line_numbers, Config, SourceFile};
+
+/// Lines of the screen used by the query prompt, the separator above the
+/// preview and the help line.
+const CHROME_HEIGHT: usize = 3;
+
+const HELP: &str = "↑/↓ move  PgUp/PgDn page  Enter open in $EDITOR  Tab toggle case  Esc quit";
+
+/// A single matching line.
+#[derive(Debug, PartialEq)]
+pub struct Hit {
+    /// Index into the sources the browser was created with.
+    pub file: usize,
+    /// 1-based line number of the match.
+    pub line_number: usize,
+}
+
+/// One row of the result list: either a file heading or a match below it.
+#[derive(Debug, PartialEq)]
+pub enum Row {
+    File(usize),
+    Hit(usize),
+}
+
+/// What the event loop should do after a key press.
+#[derive(Debug, PartialEq)]
+pub enum Action {
+    Continue,
+    Open { file: usize, line_number: usize },
+    Quit,
+}
+
+/// State of the browser, kept separate from the terminal so it can be driven
+/// by plain key events.
+pub struct App<'a> {
+    sources: &'a [SourceFile],
+    query: String,
+    case_sensitive: bool,
+    hits: Vec<Hit>,
+    rows: Vec<Row>,
+    selected: usize,
+    scroll: usize,
+}
+
+impl<'a> App<'a> {
+    pub fn new(sources: &'a [SourceFile], query: &str, case_sensitive: bool) -> App<'a> {
+        let mut app = App {
+            sources,
+            query: query.to_string(),
+            case_sensitive,
+            hits: Vec::new(),
+            rows: Vec::new(),
+            selected: 0,
+            scroll: 0,
+        };
+        app.refresh();
+        app
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn hits(&self) -> &[Hit] {
+        &self.hits
+    }
+
+    pub fn rows(&self) -> &[Row] {
+        &self.rows
+    }
+
+    pub fn selected(&self) -> Option<&Hit> {
+        self.hits.get(self.selected)
+    }
+
+    /// Re-runs the search after the query or case sensitivity changed.
+    fn refresh(&mut self) {
+        self.hits.clear();
+        self.rows.clear();
+
+        // An empty query matches every line, which isn't useful to browse.
+        if !self.query.is_empty() {
+            for (file, source) in self.sources.iter().enumerate() {
+                let matches =
+                    search_with_line_numbers(&self.query, &source.contents, self.case_sensitive);
+
+                if matches.is_empty() {
+                    continue;
+                }
+
+                self.rows.push(Row::File(file));
+                for (line_number, _) in matches {
+                    self.rows.push(Row::Hit(self.hits.len()));
+                    self.hits.push(Hit { file, line_number });
+                }
+            }
+        }
+
+        self.selected = 0;
+        self.scroll = 0;
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        if self.hits.is_empty() {
+            return;
+        }
+
+        let last = self.hits.len() - 1;
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent, page: usize) -> Action {
+        let page = page.max(1) as isize;
+
+        match key.code {
+            KeyCode::Char('c') if key.modifiers == KeyModifiers::CONTROL => return Action::Quit,
+            KeyCode::Char('u') if key.modifiers == KeyModifiers::CONTROL => {
+                self.query.clear();
+                self.refresh();
+            }
+            KeyCode::Esc => return Action::Quit,
+            KeyCode::Enter => {
+                if let Some(hit) = self.selected() {
+                    return Action::Open {
+                        file: hit.file,
+                        line_number: hit.line_number,
+                    };
+                }
+            }
+            KeyCode::Up => self.move_selection(-1),
+            KeyCode::Down => self.move_selection(1),
+            KeyCode::PageUp => self.move_selection(-page),
+            KeyCode::PageDown => self.move_selection(page),
+            KeyCode::Home => self.selected = 0,
+            KeyCode::End => self.selected = self.hits.len().saturating_sub(1),
+            KeyCode::Tab => {
+                self.case_sensitive = !self.case_sensitive;
+                self.refresh();
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.refresh();
+            }
+            // Other chords are shortcuts we don't have, not text to type.
+            KeyCode::Char(c) if (key.modifiers - KeyModifiers::SHIFT).is_empty() => {
+                self.query.push(c);
+                self.refresh();
+            }
+            _ => {}
+        }
+
+        Action::Continue
+    }
+
+    /// Scrolls the result list so that the selected match, and the heading of
+    /// its file when it is the first match, stay inside `height` rows.
+    fn scroll_into_view(&mut self, height: usize) {
+        let Some(row) = self.rows.iter().position(|r| *r == Row::Hit(self.selected)) else {
+            return;
+        };
+
+        let top = match row.checked_sub(1).map(|r| &self.rows[r]) {
+            Some(Row::File(_)) => row - 1,
+            _ => row,
+        };
+
+        if top < self.scroll {
+            self.scroll = top;
+        } else if row >= self.scroll + height {
+            self.scroll = row + 1 - height.max(1);
+        }
+    }
+}
+
+/// Runs the browser until the user quits.
+pub fn browse(config: &Config, sources: &[SourceFile]) -> Result<(), Box<dyn Error>> {
+    let mut app = App::new(sources, &config.query, config.case_sensitive);
+    let mut stdout = io::stdout();
+
+    enter_screen(&mut stdout)?;
+    let result = event_loop(&mut app, &mut stdout);
+    // Always give the terminal back, even if the loop failed.
+    leave_screen(&mut stdout)?;
+
+    result
+}
+
+fn event_loop(app: &mut App, stdout: &mut io::Stdout) -> Result<(), Box<dyn Error>> {
+    loop {
+        let (width, height) = terminal::size()?;
+        let (list_height, _) = pane_heights(height as usize);
+        draw(app, stdout, width as usize, height as usize)?;
+
+        let key = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            _ => continue,
+        };
+
+        match app.handle_key(key, list_height) {
+            Action::Continue => {}
+            Action::Quit => return Ok(()),
+            Action::Open { file, line_number } => {
+                leave_screen(stdout)?;
+                let status = editor_command(&app.sources[file].path, line_number).status();
+                enter_screen(stdout)?;
+                status?;
+            }
+        }
+    }
+}
+
+fn enter_screen(stdout: &mut io::Stdout) -> io::Result<()> {
+    terminal::enable_raw_mode()?;
+    execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)
+}
+
+fn leave_screen(stdout: &mut io::Stdout) -> io::Result<()> {
+    execute!(stdout, cursor::Show, terminal::LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()
+}
+
+/// Splits the space left over by the prompt and help lines between the result
+/// list (two thirds) and the preview (one third).
+fn pane_heights(height: usize) -> (usize, usize) {
+    let available = height.saturating_sub(CHROME_HEIGHT);
+    let preview = available / 3;
+    (available - preview, preview)
+}
+
+fn draw(app: &mut App, stdout: &mut io::Stdout, width: usize, height: usize) -> io::Result<()> {
+    let (list_height, preview_height) = pane_heights(height);
+    app.scroll_into_view(list_height);
+
+    queue!(
+        stdout,
+        terminal::Clear(ClearType::All),
+        cursor::MoveTo(0, 0)
+    )?;
+
+    let files = app
+        .rows
+        .iter()
+        .filter(|r| matches!(r, Row::File(_)))
+        .count();
+    let case = if app.case_sensitive {
+        ""
+    } else {
+        " (ignoring case)"
+    };
+    let status = format!(
+        "> {}   [{} matches in {} files{}]",
+        app.query,
+        app.hits.len(),
+        files,
+        case
+    );
+    queue!(stdout, Print(fit(&status, width)))?;
+
+    for (i, row) in app
+        .rows
+        .iter()
+        .skip(app.scroll)
+        .take(list_height)
+        .enumerate()
+    {
+        queue!(stdout, cursor::MoveTo(0, (i + 1) as u16))?;
+
+        match *row {
+            Row::File(file) => {
+                let name = app.sources[file].path.display().to_string();
+                queue!(
+                    stdout,
+                    SetAttribute(Attribute::Bold),
+                    Print(fit(&name, width)),
+                    SetAttribute(Attribute::Reset)
+                )?;
+            }
+            Row::Hit(hit) => {
+                let Hit { file, line_number } = app.hits[hit];
+                let line = line_at(&app.sources[file].contents, line_number);
+                let text = fit(&format!("  {:>5}: {}", line_number, line), width);
+
+                if hit == app.selected {
+                    queue!(
+                        stdout,
+                        SetAttribute(Attribute::Reverse),
+                        Print(text),
+                        SetAttribute(Attribute::Reset)
+                    )?;
+                } else {
+                    queue!(stdout, Print(text))?;
+                }
+            }
+        }
+    }
+
+    let separator_row = list_height + 1;
+    queue!(
+        stdout,
+        cursor::MoveTo(0, separator_row as u16),
+        Print("─".repeat(width))
+    )?;
+
+    if let Some(&Hit { file, line_number }) = app.selected() {
+        let contents = &app.sources[file].contents;
+        let before = preview_height.saturating_sub(1) / 2;
+        let first = line_number.saturating_sub(before).max(1);
+
+        for (i, (number, line)) in contents
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line))
+            .skip(first - 1)
+            .take(preview_height)
+            .enumerate()
+        {
+            let text = fit(&format!("{:>5}  {}", number, line), width);
+            queue!(stdout, cursor::MoveTo(0, (separator_row + 1 + i) as u16))?;
+
+            if number == line_number {
+                queue!(
+                    stdout,
+                    SetAttribute(Attribute::Bold),
+                    Print(text),
+                    SetAttribute(Attribute::Reset)
+                )?;
+            } else {
+                queue!(stdout, Print(text))?;
+            }
+        }
+    }
+
+    queue!(
+        stdout,
+        cursor::MoveTo(0, height.saturating_sub(1) as u16),
+        SetAttribute(Attribute::Dim),
+        Print(fit(HELP, width)),
+        SetAttribute(Attribute::Reset)
+    )?;
+
+    stdout.flush()
+}
+
+fn line_at(contents: &str, line_number: usize) -> &str {
+    contents.lines().nth(line_number - 1).unwrap_or("")
+}
+
+/// Makes `text` fit on one terminal row: tabs become spaces and anything past
+/// `width` characters is cut off.
+fn fit(text: &str, width: usize) -> String {
+    text.replace('\t', "    ").chars().take(width).collect()
+}
+
+/// Builds the command that opens `path` at `line_number` in the user's editor.
+///
+/// `$EDITOR` may contain arguments (e.g. `emacsclient -t`); the `+<line>` form is
+/// understood by vi, vim, nano, emacs and most other terminal editors.
+pub fn editor_command(path: &Path, line_number: usize) -> Command {
+    let editor = env::var("EDITOR").unwrap_or_else(|_| String::from("vi"));
+    build_editor_command(&editor, path, line_number)
+}
+
+fn build_editor_command(editor: &str, path: &Path, line_number: usize) -> Command {
+    let mut words = editor.split_whitespace();
+    let mut command = Command::new(words.next().unwrap_or("vi"));
+    command
+        .args(words)
+        .arg(format!("+{}", line_number))
+        .arg(path);
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn sources() -> Vec<SourceFile> {
+        vec![
+            SourceFile {
+                path: PathBuf::from("a.txt"),
+                contents: String::from("Rust:\nsafe, fast, productive.\nTrust me."),
+            },
+            SourceFile {
+                path: PathBuf::from("b.txt"),
+                contents: String::from("nothing here"),
+            },
+            SourceFile {
+                path: PathBuf::from("c.txt"),
+                contents: String::from("rusty\nnails"),
+            },
+        ]
+    }
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn groups_hits_by_file() {
+        let sources = sources();
+        let app = App::new(&sources, "rust", false);
+
+        assert_eq!(
+            app.rows(),
+            &[
+                Row::File(0),
+                Row::Hit(0),
+                Row::Hit(1),
+                Row::File(2),
+                Row::Hit(2)
+            ]
+        );
+        assert_eq!(
+            app.hits()[2],
+            Hit {
+                file: 2,
+                line_number: 1
+            }
+        );
+    }
+
+    #[test]
+    fn refines_query_live() {
+        let sources = sources();
+        let mut app = App::new(&sources, "rust", false);
+
+        app.handle_key(key(KeyCode::Char('y')), 10);
+        assert_eq!("rusty", app.query());
+        assert_eq!(1, app.hits().len());
+
+        app.handle_key(key(KeyCode::Backspace), 10);
+        app.handle_key(key(KeyCode::Tab), 10);
+        assert_eq!(2, app.hits().len());
+    }
+
+    #[test]
+    fn ignores_chords_in_the_query() {
+        let sources = sources();
+        let mut app = App::new(&sources, "rust", false);
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('Y'), KeyModifiers::SHIFT), 10);
+        assert_eq!("rustY", app.query());
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::ALT), 10);
+        app.handle_key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::CONTROL), 10);
+        assert_eq!("rustY", app.query());
+
+        assert_eq!(
+            Action::Quit,
+            app.handle_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL), 10)
+        );
+        assert_eq!("rustY", app.query());
+    }
+
+    #[test]
+    fn navigates_and_opens_selection() {
+        let sources = sources();
+        let mut app = App::new(&sources, "rust", false);
+
+        app.handle_key(key(KeyCode::Up), 10);
+        assert_eq!(0, app.selected);
+
+        app.handle_key(key(KeyCode::PageDown), 10);
+        assert_eq!(2, app.selected);
+
+        assert_eq!(
+            Action::Open {
+                file: 2,
+                line_number: 1
+            },
+            app.handle_key(key(KeyCode::Enter), 10)
+        );
+        assert_eq!(Action::Quit, app.handle_key(key(KeyCode::Esc), 10));
+    }
+
+    #[test]
+    fn keeps_selection_visible() {
+        let sources = sources();
+        let mut app = App::new(&sources, "rust", false);
+
+        app.handle_key(key(KeyCode::End), 2);
+        app.scroll_into_view(2);
+        assert_eq!(3, app.scroll);
+
+        app.handle_key(key(KeyCode::Home), 2);
+        app.scroll_into_view(2);
+        assert_eq!(0, app.scroll);
+    }
+
+    #[test]
+    fn editor_command_jumps_to_line() {
+        let command = build_editor_command("emacsclient -t", Path::new("a.txt"), 12);
+
+        assert_eq!("emacsclient", command.get_program());
+        assert_eq!(
+            vec!["-t", "+12", "a.txt"],
+            command.get_args().collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,137 @@
+//! Golden-file tests that run the `minigrep` binary against the fixture
+//! directories in `tests/fixtures`.
+//!
+//! Each fixture directory contains:
+//!
+//! * `args` - the command-line arguments, one per line
+//! * `env` - optional `KEY=VALUE` lines added to the environment
+//! * `stdout`, `stderr`, `status` - the expected output and exit code
+//!
+//! The binary runs with the fixture directory as its working directory, so
+//! `args` can refer to input files by relative path. Run the tests with
+//! `UPDATE_GOLDEN=1` to (re)write the expected files from the actual output.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+struct Outcome {
+    stdout: String,
+    stderr: String,
+    status: String,
+}
+
+fn fixture_dir(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+fn read_lines(path: &Path) -> Vec<String> {
+    match fs::read_to_string(path) {
+        Ok(contents) => contents.lines().map(String::from).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn run_fixture(dir: &Path) -> Outcome {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_minigrep"));
+    command
+        .current_dir(dir)
+        .args(read_lines(&dir.join("args")))
+        // The developer's own environment mustn't change the results.
+        .env_remove("CASE_INSENSITIVE");
+
+    for line in read_lines(&dir.join("env")) {
+        let (key, value) = line.split_once('=').expect("env lines are KEY=VALUE");
+        command.env(key, value);
+    }
+
+    let output = command.output().expect("failed to run minigrep");
+
+    Outcome {
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        status: format!("{}\n", output.status.code().unwrap_or(-1)),
+    }
+}
+
+fn check(name: &str) {
+    let dir = fixture_dir(name);
+    let actual = run_fixture(&dir);
+
+    let files = [
+        ("stdout", &actual.stdout),
+        ("stderr", &actual.stderr),
+        ("status", &actual.status),
+    ];
+
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        for (file, contents) in files {
+            fs::write(dir.join(file), contents).unwrap();
+        }
+        return;
+    }
+
+    for (file, contents) in files {
+        let expected = fs::read_to_string(dir.join(file))
+            .unwrap_or_else(|_| panic!("missing {}/{}, run with UPDATE_GOLDEN=1", name, file));
+
+        assert_eq!(
+            &expected, contents,
+            "{} of fixture `{}` doesn't match the golden file",
+            file, name
+        );
+    }
+}
+
+macro_rules! golden {
+    ($($name:ident),* $(,)?) => {
+        $(
+            #[test]
+            fn $name() {
+                check(stringify!($name));
+            }
+        )*
+    };
+}
+
+golden!(
+    single_file,
+    multiple_files,
+    directory,
+    case_insensitive,
+    ignore_case_flag,
+    multiline,
+    no_match,
+    missing_file,
+    missing_arguments,
+    unknown_option,
+);
+
+/// Timings differ from run to run, so `--stats` output can't be a golden file;
+/// only the counters are checked.
+#[test]
+fn stats() {
+    let actual = run_fixture(&fixture_dir("stats"));
+
+    assert_eq!("0\n", actual.status);
+    assert_eq!(
+        "input/poem.txt:Are you nobody, too?\ninput/poem.txt:How dreary to be somebody!\n",
+        actual.stdout
+    );
+
+    // binary.bin isn't searched, but its 8 bytes were still read.
+    for counter in [
+        "files scanned:      1\n",
+        "files with matches: 1\n",
+        "bytes read:         229\n",
+        "lines scanned:      9\n",
+        "matches found:      2\n",
+    ] {
+        assert!(actual.stderr.contains(counter), "{}", actual.stderr);
+    }
+}
@@ -0,0 +1,153 @@
+//! Property tests comparing the search functions against a naive oracle.
+
+use minigrep::{search, search_case_insensitive, search_multiline, search_with_line_numbers};
+use proptest::prelude::*;
+
+/// A line search written without any of the library's building blocks, used
+/// as the oracle: it splits lines and looks for the query byte by byte, so a
+/// mistake in `lines`, `contains` or `to_lowercase` can't be shared with the
+/// code under test. Case is folded for ASCII only, which is all the strategies
+/// below generate.
+fn oracle<'a>(query: &str, contents: &'a str, case_sensitive: bool) -> Vec<(usize, &'a str)> {
+    let bytes = contents.as_bytes();
+    let mut results = Vec::new();
+    let mut start = 0;
+    let mut number = 1;
+
+    while start < bytes.len() {
+        let (end, next) = match bytes[start..].iter().position(|&b| b == b'\n') {
+            // "\r\n" ends a line just like "\n" does.
+            Some(i) if i > 0 && bytes[start + i - 1] == b'\r' => (start + i - 1, start + i + 1),
+            Some(i) => (start + i, start + i + 1),
+            None => (bytes.len(), bytes.len()),
+        };
+
+        if contains(&bytes[start..end], query.as_bytes(), case_sensitive) {
+            results.push((number, &contents[start..end]));
+        }
+
+        start = next;
+        number += 1;
+    }
+
+    results
+}
+
+/// Returns true if `needle` appears at any offset of `haystack`.
+fn contains(haystack: &[u8], needle: &[u8], case_sensitive: bool) -> bool {
+    let same = |a: u8, b: u8| {
+        if case_sensitive {
+            a == b
+        } else {
+            a.eq_ignore_ascii_case(&b)
+        }
+    };
+
+    (0..=haystack.len()).any(|offset| {
+        offset + needle.len() <= haystack.len()
+            && needle
+                .iter()
+                .enumerate()
+                .all(|(i, &n)| same(haystack[offset + i], n))
+    })
+}
+
+/// Returns true if every item of `needle` appears in `haystack` in the same
+/// order. Slices are compared by address so that equal lines at different
+/// positions can't stand in for each other.
+fn is_subsequence(needle: &[&str], haystack: &[&str]) -> bool {
+    let mut haystack = haystack.iter();
+
+    needle
+        .iter()
+        .all(|n| haystack.any(|h| std::ptr::eq(*n, *h)))
+}
+
+fn contents() -> impl Strategy<Value = String> {
+    // A small alphabet with mixed case, blank lines and Windows line endings
+    // makes matches (and the edge cases around them) likely.
+    prop::collection::vec("[aAbB \r]{0,8}", 0..12).prop_map(|lines| lines.join("\n"))
+}
+
+fn query() -> impl Strategy<Value = String> {
+    "[aAbB ]{0,3}"
+}
+
+proptest! {
+    #[test]
+    fn search_is_a_subsequence_of_lines(query in query(), contents in contents()) {
+        let lines: Vec<&str> = contents.lines().collect();
+
+        prop_assert!(is_subsequence(&search(&query, &contents), &lines));
+        prop_assert!(is_subsequence(&search_case_insensitive(&query, &contents), &lines));
+    }
+
+    #[test]
+    fn search_agrees_with_oracle(query in query(), contents in contents()) {
+        let expected: Vec<&str> = oracle(&query, &contents, true)
+            .into_iter()
+            .map(|(_, line)| line)
+            .collect();
+
+        prop_assert_eq!(expected, search(&query, &contents));
+    }
+
+    #[test]
+    fn case_insensitive_search_agrees_with_oracle(query in query(), contents in contents()) {
+        let expected: Vec<&str> = oracle(&query, &contents, false)
+            .into_iter()
+            .map(|(_, line)| line)
+            .collect();
+
+        prop_assert_eq!(expected, search_case_insensitive(&query, &contents));
+    }
+
+    #[test]
+    fn line_numbers_agree_with_oracle(
+        query in query(),
+        contents in contents(),
+        case_sensitive in any::<bool>(),
+    ) {
+        prop_assert_eq!(
+            oracle(&query, &contents, case_sensitive),
+            search_with_line_numbers(&query, &contents, case_sensitive)
+        );
+    }
+
+    #[test]
+    fn every_line_matches_empty_query(contents in contents()) {
+        prop_assert_eq!(contents.lines().count(), search("", &contents).len());
+    }
+
+    #[test]
+    fn multiline_without_whitespace_finds_the_same_lines(
+        query in "[aAbB]{1,3}",
+        contents in contents(),
+        case_sensitive in any::<bool>(),
+    ) {
+        let mut lines: Vec<usize> = search_multiline(&query, &contents, case_sensitive)
+            .iter()
+            .inspect(|m| assert_eq!(m.start_line, m.end_line))
+            .map(|m| m.start_line)
+            .collect();
+        lines.dedup();
+
+        let expected: Vec<usize> = search_with_line_numbers(&query, &contents, case_sensitive)
+            .into_iter()
+            .map(|(number, _)| number)
+            .collect();
+
+        prop_assert_eq!(expected, lines);
+    }
+
+    #[test]
+    fn multiline_matches_normalize_to_the_query(query in query(), contents in contents()) {
+        let normalize = |s: &str| s.split_whitespace().collect::<Vec<_>>().join(" ");
+
+        for m in search_multiline(&query, &contents, true) {
+            prop_assert_eq!(normalize(&query), normalize(m.text));
+            prop_assert!(m.start_line <= m.end_line);
+            prop_assert!(m.lines.contains(m.text.trim_end_matches('\r')));
+        }
+    }
+}
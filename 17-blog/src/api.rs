@@ -0,0 +1,378 @@
+//! A JSON over HTTP interface to the post workflow.
+//!
+//! - `GET /posts` lists every post.
+//! - `POST /posts` creates a draft from
+//!   `{"text": "...", "authors": ["..."], "required_approvals": 2}`, where
+//!   every field is optional.
+//! - `GET /posts/{id}` shows one post.
+//! - `POST /posts/{id}/text` appends `{"text": "..."}` to a draft.
+//! - `POST /posts/{id}/{action}` performs an action.
+//!
+//! Every `POST` also says who is asking, as
+//! `{"actor": "...", "role": "author" | "editor" | "admin"}` next to the
+//! other fields.
+//!
+//! `{action}` is one of `request-review`, `approve`, `reject`,
+//! `archive`, `unarchive`, `unpublish` or `delete`. Every response is a JSON
+//! object; errors look like `{"error": "..."}`. A post is returned as
+//! `{"id", "status", "content", "authors", "approvals"}`, where `content`
+//! is what [`Post::content`] shows readers. Actions and edits are checked
+//! against the post's [`Policy`](crate::policy::Policy); a denied one is a
+//! `403`.
+//!
+//! The API takes the actor's word for who they are; it is meant to sit
+//! behind something that authenticates them. Bodies larger than
+//! [`MAX_BODY_BYTES`] are refused with a `413`.
+//!
+//! [`Api::handle`] does the routing and knows nothing about sockets, and
+//! [`serve`] connects it to a `tiny_http` server.
+
+use std::io::Read;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{
+    error::{EditError, RepositoryError, WorkflowError},
+    policy::{Actor, Role},
+    repository::PostRepository,
+    Post,
+};
+
+/// The largest request body the API accepts, in bytes.
+pub const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// An HTTP response before it is written to the connection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub body: Value,
+}
+
+impl Response {
+    fn ok(body: Value) -> Response {
+        Response { status: 200, body }
+    }
+
+    fn error(status: u16, message: impl ToString) -> Response {
+        Response {
+            status,
+            body: json!({ "error": message.to_string() }),
+        }
+    }
+}
+
+impl From<RepositoryError> for Response {
+    fn from(e: RepositoryError) -> Response {
+        match e {
+            // Ids come from the URL, so an invalid one is just a post that
+            // can't exist.
+            RepositoryError::InvalidId(_) => Response::error(404, "no such post"),
+            RepositoryError::Conflict(_) => Response::error(409, e),
+            e => Response::error(500, e),
+        }
+    }
+}
+
+impl From<WorkflowError> for Response {
+    fn from(e: WorkflowError) -> Response {
+        match e {
+            WorkflowError::Permission(_) => Response::error(403, e),
+            WorkflowError::Transition(_) | WorkflowError::Blocked { .. } => Response::error(409, e),
+        }
+    }
+}
+
+impl From<EditError> for Response {
+    fn from(e: EditError) -> Response {
+        match e {
+            EditError::NotAllowed(_) => Response::error(403, e),
+            e => Response::error(409, e),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct NewPost {
+    #[serde(flatten)]
+    actor: ActorBody,
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    authors: Vec<String>,
+    required_approvals: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct Text {
+    #[serde(flatten)]
+    actor: ActorBody,
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct ActorBody {
+    actor: String,
+    role: Role,
+}
+
+impl ActorBody {
+    fn actor(&self) -> Actor {
+        Actor::new(&self.actor, self.role)
+    }
+}
+
+/// The post workflow, backed by a repository.
+pub struct Api<R> {
+    repository: R,
+    /// The lowest id this API hasn't handed out yet.
+    next_id: u64,
+}
+
+impl<R: PostRepository> Api<R> {
+    pub fn new(repository: R) -> Api<R> {
+        Api {
+            repository,
+            next_id: 1,
+        }
+    }
+
+    pub fn repository(&self) -> &R {
+        &self.repository
+    }
+
+    /// Answers one request. `path` may include a query string, which is
+    /// ignored.
+    pub fn handle(&mut self, method: &str, path: &str, body: &str) -> Response {
+        if body.len() > MAX_BODY_BYTES {
+            return too_large();
+        }
+
+        let path = path.split('?').next().unwrap_or_default();
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+        let result = match (method, segments.as_slice()) {
+            ("GET", ["posts"]) => self.list(),
+            ("POST", ["posts"]) => self.create(body),
+            ("GET", ["posts", id]) => self.show(id),
+            ("POST", ["posts", id, "text"]) => self.add_text(id, body),
+            ("POST", ["posts", id, action]) => self.transition(id, action, body),
+            (_, ["posts"] | ["posts", _] | ["posts", _, _]) => {
+                Err(Response::error(405, "method not allowed"))
+            }
+            _ => Err(Response::error(404, "not found")),
+        };
+
+        result.unwrap_or_else(|response| response)
+    }
+
+    fn list(&self) -> Result<Response, Response> {
+        let mut posts = Vec::new();
+
+        for id in self.repository.ids()? {
+            if let Some(post) = self.repository.load(&id)? {
+                posts.push(view(&id, &post));
+            }
+        }
+
+        Ok(Response::ok(json!({ "posts": posts })))
+    }
+
+    fn create(&mut self, body: &str) -> Result<Response, Response> {
+        let new: NewPost = parse(body)?;
+
+        let mut post = Post::with_required_approvals(new.required_approvals.unwrap_or(1));
+        // The authors come first, so that one of them may write the text.
+        for author in &new.authors {
+            post.add_author(author);
+        }
+        // An empty first revision would only clutter the history.
+        if !new.text.is_empty() {
+            post.add_text(&new.actor.actor(), &new.text)?;
+        }
+
+        // Ids are handed out in sequence after the highest one stored or
+        // handed out before, so that the id of a post that was removed isn't
+        // given to a new one, at least not while the API is running.
+        let highest = self
+            .repository
+            .ids()?
+            .iter()
+            .filter_map(|id| id.parse::<u64>().ok())
+            .max()
+            .unwrap_or(0);
+        let id = self.next_id.max(highest + 1);
+        self.next_id = id + 1;
+        let id = id.to_string();
+
+        self.repository.save(&id, &post)?;
+
+        Ok(Response {
+            status: 201,
+            body: view(&id, &post),
+        })
+    }
+
+    fn show(&self, id: &str) -> Result<Response, Response> {
+        let post = self.load(id)?;
+        Ok(Response::ok(view(id, &post)))
+    }
+
+    fn add_text(&mut self, id: &str, body: &str) -> Result<Response, Response> {
+        let text: Text = parse(body)?;
+
+        let mut post = self.load(id)?;
+        post.add_text(&text.actor.actor(), &text.text)?;
+        self.repository.save(id, &post)?;
+
+        Ok(Response::ok(view(id, &post)))
+    }
+
+    fn transition(&mut self, id: &str, action: &str, body: &str) -> Result<Response, Response> {
+        let perform = match action {
+            "request-review" => Post::request_review,
+            "approve" => Post::approve,
+            "reject" => Post::reject,
+            "archive" => Post::archive,
+            "unarchive" => Post::unarchive,
+            "unpublish" => Post::unpublish,
+            "delete" => Post::delete,
+            _ => return Err(Response::error(404, "not found")),
+        };
+
+        let body: ActorBody = parse(body)?;
+
+        let mut post = self.load(id)?;
+        perform(&mut post, &body.actor())?;
+        self.repository.save(id, &post)?;
+
+        Ok(Response::ok(view(id, &post)))
+    }
+
+    fn load(&self, id: &str) -> Result<Post, Response> {
+        self.repository
+            .load(id)?
+            .ok_or_else(|| Response::error(404, "no such post"))
+    }
+}
+
+fn too_large() -> Response {
+    Response::error(
+        413,
+        format!("request body larger than {} bytes", MAX_BODY_BYTES),
+    )
+}
+
+fn parse<'a, T: Deserialize<'a>>(body: &'a str) -> Result<T, Response> {
+    serde_json::from_str(body).map_err(|e| Response::error(400, format!("invalid body: {}", e)))
+}
+
+fn view(id: &str, post: &Post) -> Value {
+    json!({
+        "id": id,
+        "status": post.status(),
+        "content": post.content(),
+        "authors": post.authors(),
+        "approvals": post.approvals(),
+    })
+}
+
+/// Answers requests arriving at `server` until it is shut down.
+pub fn serve<R: PostRepository>(server: &tiny_http::Server, api: &mut Api<R>) {
+    let content_type =
+        tiny_http::Header::from_bytes("Content-Type", "application/json").expect("valid header");
+
+    for mut request in server.incoming_requests() {
+        // Read one byte past the limit, to tell a body at the limit from one
+        // over it without reading all of a huge one.
+        let mut body = Vec::new();
+        let read = request
+            .as_reader()
+            .take(MAX_BODY_BYTES as u64 + 1)
+            .read_to_end(&mut body);
+
+        let response = match read {
+            Ok(_) if body.len() > MAX_BODY_BYTES => too_large(),
+            Ok(_) => match String::from_utf8(body) {
+                Ok(body) => api.handle(request.method().as_str(), request.url(), &body),
+                Err(e) => Response::error(400, format!("unreadable body: {}", e)),
+            },
+            Err(e) => Response::error(400, format!("unreadable body: {}", e)),
+        };
+
+        let http_response = tiny_http::Response::from_string(response.body.to_string())
+            .with_status_code(response.status)
+            .with_header(content_type.clone());
+
+        // The client may have gone away; there is nobody to tell about it.
+        let _ = request.respond(http_response);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::InMemoryRepository;
+
+    #[test]
+    fn routes_requests() {
+        let mut api = Api::new(InMemoryRepository::new());
+
+        let created = api.handle(
+            "POST",
+            "/posts",
+            r#"{"text": "Hello", "actor": "alice", "role": "editor"}"#,
+        );
+        assert_eq!(201, created.status);
+        assert_eq!("1", created.body["id"]);
+        assert_eq!("draft", created.body["status"]);
+
+        assert_eq!(405, api.handle("DELETE", "/posts/1", "").status);
+        assert_eq!(404, api.handle("GET", "/nothing", "").status);
+        assert_eq!(404, api.handle("GET", "/posts/2", "").status);
+        assert_eq!(404, api.handle("GET", "/posts/..", "").status);
+        assert_eq!(404, api.handle("POST", "/posts/1/frobnicate", "{}").status);
+        assert_eq!(400, api.handle("POST", "/posts/1/approve", "{}").status);
+        assert_eq!(
+            400,
+            api.handle(
+                "POST",
+                "/posts/1/approve",
+                r#"{"actor": "alice", "role": "boss"}"#
+            )
+            .status
+        );
+    }
+
+    #[test]
+    fn reports_workflow_errors() {
+        let mut api = Api::new(InMemoryRepository::new());
+        api.handle(
+            "POST",
+            "/posts",
+            r#"{"authors": ["alice"], "actor": "alice", "role": "author"}"#,
+        );
+
+        let response = api.handle(
+            "POST",
+            "/posts/1/approve",
+            r#"{"actor": "alice", "role": "editor"}"#,
+        );
+        assert_eq!(403, response.status);
+        assert_eq!(
+            "editor alice may not approve this post",
+            response.body["error"]
+        );
+
+        let response = api.handle(
+            "POST",
+            "/posts/1/approve",
+            r#"{"actor": "bob", "role": "editor"}"#,
+        );
+        assert_eq!(409, response.status);
+        assert_eq!(
+            "cannot approve a post in the draft state",
+            response.body["error"]
+        );
+    }
+}
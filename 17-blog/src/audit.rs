@@ -0,0 +1,19 @@
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Action, Status};
+
+/// A record of one successful transition of a post.
+///
+/// Actions that leave a post in the same state, such as an approval that
+/// isn't yet enough to publish it, are recorded too.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub action: Action,
+    pub from: Status,
+    pub to: Status,
+    /// Who performed the action.
+    pub actor: String,
+    pub at: SystemTime,
+}
@@ -0,0 +1,23 @@
+use std::{env, process};
+
+use blog::{
+    api::{self, Api},
+    repository::InMemoryRepository,
+};
+
+fn main() {
+    // The address to listen on can be given as the only argument.
+    let addr = env::args()
+        .nth(1)
+        .unwrap_or_else(|| String::from("127.0.0.1:8080"));
+
+    let server = tiny_http::Server::http(&addr).unwrap_or_else(|e| {
+        eprintln!("Problem listening on {}: {}", addr, e);
+        process::exit(1);
+    });
+
+    println!("Listening on http://{}", server.server_addr());
+
+    let mut api = Api::new(InMemoryRepository::new());
+    api::serve(&server, &mut api);
+}
@@ -0,0 +1,188 @@
+//! A set of posts addressed by slug, and queries over them.
+
+use std::collections::BTreeMap;
+
+use crate::{metadata, Post, Status};
+
+/// Posts keyed by their URL slug.
+///
+/// A post gets its slug from its title when it is inserted and keeps it
+/// afterwards, even if the title changes, so that links to it keep working.
+#[derive(Default)]
+pub struct Collection {
+    posts: BTreeMap<String, Post>,
+}
+
+impl Collection {
+    pub fn new() -> Collection {
+        Collection::default()
+    }
+
+    /// Adds `post` and returns the slug it can be found under. Posts with
+    /// the same title get numbered slugs: `hello`, `hello-2`, ...
+    pub fn insert(&mut self, post: Post) -> String {
+        let slug = metadata::unique_slug(post.title(), |slug| self.posts.contains_key(slug));
+        self.posts.insert(slug.clone(), post);
+        slug
+    }
+
+    pub fn get(&self, slug: &str) -> Option<&Post> {
+        self.posts.get(slug)
+    }
+
+    pub fn get_mut(&mut self, slug: &str) -> Option<&mut Post> {
+        self.posts.get_mut(slug)
+    }
+
+    pub fn remove(&mut self, slug: &str) -> Option<Post> {
+        self.posts.remove(slug)
+    }
+
+    pub fn len(&self) -> usize {
+        self.posts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.posts.is_empty()
+    }
+
+    /// Every post with its slug, in slug order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Post)> {
+        self.posts.iter().map(|(slug, post)| (slug.as_str(), post))
+    }
+
+    /// Starts a query matching every post; narrow it down with
+    /// [`Query::tag`], [`Query::author`] and [`Query::status`].
+    pub fn query(&self) -> Query<'_> {
+        Query {
+            collection: self,
+            tag: None,
+            author: None,
+            status: None,
+        }
+    }
+}
+
+/// Posts of a [`Collection`] that match all of the given filters.
+pub struct Query<'a> {
+    collection: &'a Collection,
+    tag: Option<String>,
+    author: Option<String>,
+    status: Option<Status>,
+}
+
+impl<'a> Query<'a> {
+    /// Only posts with this tag. Tags are compared case-insensitively.
+    pub fn tag(mut self, tag: &str) -> Query<'a> {
+        self.tag = Some(tag.to_lowercase());
+        self
+    }
+
+    /// Only posts with `author` among their authors.
+    pub fn author(mut self, author: &str) -> Query<'a> {
+        self.author = Some(author.to_string());
+        self
+    }
+
+    /// Only posts in this state, as reported by [`Post::status`].
+    pub fn status(mut self, status: Status) -> Query<'a> {
+        self.status = Some(status);
+        self
+    }
+
+    fn matches(&self, post: &Post) -> bool {
+        self.tag.as_deref().is_none_or(|tag| post.has_tag(tag))
+            && self
+                .author
+                .as_deref()
+                .is_none_or(|author| post.authors().iter().any(|a| a == author))
+            && self.status.is_none_or(|status| post.status() == status)
+    }
+
+    /// The matching posts with their slugs, in slug order.
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, &'a Post)> + '_ {
+        self.collection
+            .iter()
+            .filter(|(_, post)| self.matches(post))
+    }
+
+    /// The slugs of the matching posts.
+    pub fn slugs(&self) -> Vec<&'a str> {
+        self.iter().map(|(slug, _)| slug).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::Actor;
+
+    fn post(title: &str, author: &str, tags: &[&str]) -> Post {
+        let mut post = Post::new();
+        post.add_author(author);
+        post.set_title(&Actor::author(author), title).unwrap();
+        for tag in tags {
+            post.add_tag(tag);
+        }
+        post
+    }
+
+    #[test]
+    fn gives_each_post_its_own_slug() {
+        let mut posts = Collection::new();
+
+        assert_eq!(
+            "hello-world",
+            posts.insert(post("Hello, world", "alice", &[]))
+        );
+        assert_eq!(
+            "hello-world-2",
+            posts.insert(post("Hello World!", "bob", &[]))
+        );
+
+        // Retitling doesn't move the post.
+        posts
+            .get_mut("hello-world")
+            .unwrap()
+            .set_title(&Actor::editor("carol"), "Goodbye")
+            .unwrap();
+        assert_eq!("Goodbye", posts.get("hello-world").unwrap().title());
+        assert_eq!(
+            "hello-world-3",
+            posts.insert(post("Hello world", "carol", &[]))
+        );
+    }
+
+    #[test]
+    fn filters_by_tag_author_and_status() {
+        let mut posts = Collection::new();
+        posts.insert(post("Salad", "alice", &["Food", "health"]));
+        posts.insert(post("Sandwich", "bob", &["food"]));
+        posts.insert(post("Running", "alice", &["health"]));
+
+        let mut published = post("Soup", "alice", &["food"]);
+        published.request_review(&Actor::author("alice")).unwrap();
+        published.approve(&Actor::editor("bob")).unwrap();
+        posts.insert(published);
+
+        assert_eq!(
+            vec!["salad", "sandwich", "soup"],
+            posts.query().tag("FOOD").slugs()
+        );
+        assert_eq!(
+            vec!["running", "salad", "soup"],
+            posts.query().author("alice").slugs()
+        );
+        assert_eq!(
+            vec!["salad"],
+            posts
+                .query()
+                .tag("food")
+                .author("alice")
+                .status(Status::Draft)
+                .slugs()
+        );
+        assert_eq!(4, posts.query().iter().count());
+        assert!(posts.query().author("nobody").slugs().is_empty());
+    }
+}
@@ -0,0 +1,343 @@
+use std::{error::Error, fmt, io, ops::Range};
+
+use crate::{policy::Actor, Action, Status};
+
+/// Returned when an action isn't allowed in the state a post is in, e.g.
+/// approving a post that is still a draft.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransitionError {
+    /// The state the post was (and still is) in.
+    pub status: Status,
+    /// What was attempted.
+    pub action: Action,
+}
+
+impl fmt::Display for TransitionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "cannot {} a post in the {} state",
+            self.action, self.status
+        )
+    }
+}
+
+impl Error for TransitionError {}
+
+/// Returned when the post's policy doesn't let an actor perform an action,
+/// e.g. an editor approving their own post.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PermissionError {
+    pub actor: Actor,
+    pub action: Action,
+}
+
+impl fmt::Display for PermissionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} may not {} this post", self.actor, self.action)
+    }
+}
+
+impl Error for PermissionError {}
+
+/// Returned when a workflow action on a post fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorkflowError {
+    /// The actor isn't allowed to perform the action.
+    Permission(PermissionError),
+    /// The action isn't possible in the post's current state.
+    Transition(TransitionError),
+    /// The post can't be approved because these review threads are blocking
+    /// and unresolved.
+    Blocked { action: Action, threads: Vec<usize> },
+}
+
+impl fmt::Display for WorkflowError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WorkflowError::Permission(e) => e.fmt(f),
+            WorkflowError::Transition(e) => e.fmt(f),
+            WorkflowError::Blocked { action, threads } => {
+                let threads: Vec<String> = threads.iter().map(|id| id.to_string()).collect();
+                write!(
+                    f,
+                    "cannot {} a post with unresolved blocking comments (threads {})",
+                    action,
+                    threads.join(", ")
+                )
+            }
+        }
+    }
+}
+
+impl Error for WorkflowError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            WorkflowError::Permission(e) => Some(e),
+            WorkflowError::Transition(e) => Some(e),
+            WorkflowError::Blocked { .. } => None,
+        }
+    }
+}
+
+impl From<PermissionError> for WorkflowError {
+    fn from(e: PermissionError) -> WorkflowError {
+        WorkflowError::Permission(e)
+    }
+}
+
+impl From<TransitionError> for WorkflowError {
+    fn from(e: TransitionError) -> WorkflowError {
+        WorkflowError::Transition(e)
+    }
+}
+
+/// Returned by a [`Machine`](crate::machine::Machine) that has no
+/// transition for an event in a state, or none whose guard passes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoTransition<S, E> {
+    pub state: S,
+    pub event: E,
+}
+
+impl<S: fmt::Display, E: fmt::Display> fmt::Display for NoTransition<S, E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "no transition for {} in the {} state",
+            self.event, self.state
+        )
+    }
+}
+
+impl<S: fmt::Debug + fmt::Display, E: fmt::Debug + fmt::Display> Error for NoTransition<S, E> {}
+
+impl From<NoTransition<Status, Action>> for TransitionError {
+    fn from(e: NoTransition<Status, Action>) -> TransitionError {
+        TransitionError {
+            status: e.state,
+            action: e.event,
+        }
+    }
+}
+
+/// Returned when the content of a post can't be changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditError {
+    /// The post's policy doesn't let this actor edit it.
+    NotAllowed(Actor),
+    /// The post is in a state that can't be edited; see
+    /// [`lifecycle::EDITABLE`](crate::lifecycle::EDITABLE).
+    NotEditable(Status),
+    /// There is no revision with this number.
+    UnknownRevision(usize),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EditError::NotAllowed(actor) => write!(f, "{} may not edit this post", actor),
+            EditError::NotEditable(status) => {
+                write!(f, "cannot edit a post in the {} state", status)
+            }
+            EditError::UnknownRevision(number) => write!(f, "there is no revision {}", number),
+        }
+    }
+}
+
+impl Error for EditError {}
+
+/// Returned when a review comment can't be added or a thread can't be
+/// changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReviewError {
+    /// New threads can only be started on posts pending review, and threads
+    /// can only be replied to or resolved until the post is approved.
+    NotInReview(Status),
+    /// The range isn't within the content, or doesn't fall on character
+    /// boundaries.
+    InvalidRange(Range<usize>),
+    /// There is no thread with this id.
+    UnknownThread(usize),
+    /// The post's policy doesn't let this actor resolve or unresolve the
+    /// thread.
+    NotAllowed { actor: Actor, thread: usize },
+}
+
+impl fmt::Display for ReviewError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReviewError::NotInReview(status) => {
+                write!(f, "cannot comment on a post in the {} state", status)
+            }
+            ReviewError::InvalidRange(range) => {
+                write!(
+                    f,
+                    "{}..{} is not a range of the content",
+                    range.start, range.end
+                )
+            }
+            ReviewError::UnknownThread(id) => write!(f, "there is no thread {}", id),
+            ReviewError::NotAllowed { actor, thread } => {
+                write!(
+                    f,
+                    "{} may not resolve or unresolve thread {}",
+                    actor, thread
+                )
+            }
+        }
+    }
+}
+
+impl Error for ReviewError {}
+
+/// Returned when a post can't be saved to or loaded from a repository.
+#[derive(Debug)]
+pub enum RepositoryError {
+    /// Ids may only contain ASCII letters, digits, `-` and `_`.
+    InvalidId(String),
+    Io(io::Error),
+    /// A stored post couldn't be read back or written out as JSON.
+    Format(serde_json::Error),
+    /// The stored history of the post isn't the one being saved on top of,
+    /// e.g. because someone else saved it first.
+    Conflict(String),
+}
+
+impl fmt::Display for RepositoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RepositoryError::InvalidId(id) => write!(f, "invalid post id {:?}", id),
+            RepositoryError::Io(e) => write!(f, "storage error: {}", e),
+            RepositoryError::Format(e) => write!(f, "malformed post: {}", e),
+            RepositoryError::Conflict(id) => {
+                write!(f, "post {:?} was changed since it was loaded", id)
+            }
+        }
+    }
+}
+
+impl Error for RepositoryError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RepositoryError::InvalidId(_) | RepositoryError::Conflict(_) => None,
+            RepositoryError::Io(e) => Some(e),
+            RepositoryError::Format(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for RepositoryError {
+    fn from(e: io::Error) -> RepositoryError {
+        RepositoryError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for RepositoryError {
+    fn from(e: serde_json::Error) -> RepositoryError {
+        RepositoryError::Format(e)
+    }
+}
+
+/// Returned when a static site can't be built.
+#[derive(Debug)]
+pub enum SiteError {
+    /// The posts couldn't be read.
+    Repository(RepositoryError),
+    /// The site couldn't be written, or a template couldn't be read.
+    Io(io::Error),
+}
+
+impl fmt::Display for SiteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SiteError::Repository(e) => e.fmt(f),
+            SiteError::Io(e) => write!(f, "cannot write site: {}", e),
+        }
+    }
+}
+
+impl Error for SiteError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SiteError::Repository(e) => Some(e),
+            SiteError::Io(e) => Some(e),
+        }
+    }
+}
+
+impl From<RepositoryError> for SiteError {
+    fn from(e: RepositoryError) -> SiteError {
+        SiteError::Repository(e)
+    }
+}
+
+impl From<io::Error> for SiteError {
+    fn from(e: io::Error) -> SiteError {
+        SiteError::Io(e)
+    }
+}
+
+/// Returned when a change to an event-sourced post fails, or its events
+/// can't be replayed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventError {
+    Workflow(WorkflowError),
+    Edit(EditError),
+    Review(ReviewError),
+    /// A post is only created once, by its first event.
+    AlreadyCreated,
+    /// The events don't start with the creation of the post.
+    NotCreated,
+    /// The event at `index` doesn't apply to the post rebuilt from the
+    /// events before it.
+    Replay {
+        index: usize,
+        cause: Box<EventError>,
+    },
+}
+
+impl fmt::Display for EventError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EventError::Workflow(e) => e.fmt(f),
+            EventError::Edit(e) => e.fmt(f),
+            EventError::Review(e) => e.fmt(f),
+            EventError::AlreadyCreated => f.write_str("the post has already been created"),
+            EventError::NotCreated => f.write_str("the events don't start with a creation"),
+            EventError::Replay { index, cause } => {
+                write!(f, "cannot replay event {}: {}", index, cause)
+            }
+        }
+    }
+}
+
+impl Error for EventError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EventError::Workflow(e) => Some(e),
+            EventError::Edit(e) => Some(e),
+            EventError::Review(e) => Some(e),
+            EventError::AlreadyCreated | EventError::NotCreated => None,
+            EventError::Replay { cause, .. } => Some(cause.as_ref()),
+        }
+    }
+}
+
+impl From<WorkflowError> for EventError {
+    fn from(e: WorkflowError) -> EventError {
+        EventError::Workflow(e)
+    }
+}
+
+impl From<EditError> for EventError {
+    fn from(e: EditError) -> EventError {
+        EventError::Edit(e)
+    }
+}
+
+impl From<ReviewError> for EventError {
+    fn from(e: ReviewError) -> EventError {
+        EventError::Review(e)
+    }
+}
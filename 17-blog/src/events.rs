@@ -0,0 +1,701 @@
+//! An event-sourced version of [`Post`].
+//!
+//! Instead of keeping only the latest state, an [`EventSourcedPost`] keeps
+//! every change ever made to it as an [`Event`] and rebuilds the post by
+//! replaying them. That makes it possible to ask what the post looked like at
+//! any point in the past, and to store posts as append-only logs.
+//!
+//! Events are replayed through the ordinary [`Post`] methods, so a replayed
+//! post goes through the same checks, and ends up with the same revisions,
+//! audit log and review threads, as one built up by hand.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clock::{Clock, SystemClock},
+    error::{EventError, RepositoryError},
+    policy::Actor,
+    repository::{check_id, ids_in},
+    Post,
+};
+
+/// A change to a post.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventKind {
+    /// Always the first event, and only ever the first.
+    Created {
+        required_approvals: usize,
+    },
+    TextAdded {
+        actor: Actor,
+        text: String,
+    },
+    RevisionRestored {
+        actor: Actor,
+        revision: usize,
+    },
+    TitleSet {
+        actor: Actor,
+        title: String,
+    },
+    AuthorAdded {
+        author: String,
+    },
+    TagAdded {
+        tag: String,
+    },
+    TagRemoved {
+        tag: String,
+    },
+    ReviewRequested {
+        actor: Actor,
+    },
+    Approved {
+        actor: Actor,
+    },
+    Scheduled {
+        actor: Actor,
+        publish_at: SystemTime,
+    },
+    Rejected {
+        actor: Actor,
+    },
+    Archived {
+        actor: Actor,
+    },
+    Unarchived {
+        actor: Actor,
+    },
+    Unpublished {
+        actor: Actor,
+    },
+    Deleted {
+        actor: Actor,
+    },
+    Commented {
+        author: String,
+        range: Range<usize>,
+        text: String,
+        blocking: bool,
+    },
+    Replied {
+        thread: usize,
+        author: String,
+        text: String,
+    },
+    Resolved {
+        thread: usize,
+        actor: Actor,
+    },
+    Unresolved {
+        thread: usize,
+        actor: Actor,
+    },
+}
+
+/// A change and when it happened.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Event {
+    pub at: SystemTime,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+/// The clock of a post being rebuilt from events: it stands still at the
+/// time of the event being applied, and otherwise follows the real clock.
+struct EventClock {
+    frozen: Mutex<Option<SystemTime>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl EventClock {
+    fn freeze(&self, at: Option<SystemTime>) {
+        *self.frozen.lock().unwrap() = at;
+    }
+}
+
+impl Clock for EventClock {
+    fn now(&self) -> SystemTime {
+        self.frozen
+            .lock()
+            .unwrap()
+            .unwrap_or_else(|| self.clock.now())
+    }
+}
+
+pub struct EventSourcedPost {
+    events: Vec<Event>,
+    post: Post,
+    clock: Arc<EventClock>,
+}
+
+impl EventSourcedPost {
+    /// Starts a new post, recording its creation.
+    pub fn new(required_approvals: usize) -> EventSourcedPost {
+        EventSourcedPost::with_clock(required_approvals, Arc::new(SystemClock))
+    }
+
+    /// Like [`EventSourcedPost::new`], but events are stamped with the time
+    /// from `clock`.
+    pub fn with_clock(required_approvals: usize, clock: Arc<dyn Clock>) -> EventSourcedPost {
+        let event = Event {
+            at: clock.now(),
+            kind: EventKind::Created { required_approvals },
+        };
+
+        EventSourcedPost::replay_with_clock(vec![event], clock)
+            .expect("a creation event always applies")
+    }
+
+    /// Rebuilds a post from its events, oldest first.
+    pub fn replay(events: Vec<Event>) -> Result<EventSourcedPost, EventError> {
+        EventSourcedPost::replay_with_clock(events, Arc::new(SystemClock))
+    }
+
+    fn replay_with_clock(
+        events: Vec<Event>,
+        clock: Arc<dyn Clock>,
+    ) -> Result<EventSourcedPost, EventError> {
+        let clock = Arc::new(EventClock {
+            frozen: Mutex::new(None),
+            clock,
+        });
+        let post = rebuild(&events, clock.clone())?;
+        clock.freeze(None);
+
+        Ok(EventSourcedPost {
+            events,
+            post,
+            clock,
+        })
+    }
+
+    /// The post as it is now.
+    pub fn post(&self) -> &Post {
+        &self.post
+    }
+
+    /// Every event so far, oldest first.
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// Applies `kind` to the post and, if that succeeds, appends it to the
+    /// log. A change that fails leaves both the post and the log as they
+    /// were.
+    pub fn execute(&mut self, kind: EventKind) -> Result<(), EventError> {
+        let at = self.clock.clock.now();
+
+        // Everything the change does happens at the one time the event is
+        // stamped with, as it will when the event is replayed.
+        self.clock.freeze(Some(at));
+        let result = apply(&mut self.post, &kind);
+        self.clock.freeze(None);
+
+        result?;
+        self.events.push(Event { at, kind });
+        Ok(())
+    }
+
+    /// The post as it was at `time`: rebuilt from the events up to and
+    /// including `time`, and with a clock that stands still at `time`, so
+    /// that e.g. a post scheduled for later still shows no content.
+    pub fn as_of(&self, time: SystemTime) -> Result<Post, EventError> {
+        let count = self
+            .events
+            .iter()
+            .take_while(|event| event.at <= time)
+            .count();
+
+        let clock = Arc::new(EventClock {
+            frozen: Mutex::new(None),
+            clock: self.clock.clock.clone(),
+        });
+        let post = rebuild(&self.events[..count], clock.clone())?;
+        clock.freeze(Some(time));
+        Ok(post)
+    }
+}
+
+fn rebuild(events: &[Event], clock: Arc<EventClock>) -> Result<Post, EventError> {
+    let Some(Event {
+        at,
+        kind: EventKind::Created { required_approvals },
+    }) = events.first()
+    else {
+        return Err(EventError::NotCreated);
+    };
+
+    clock.freeze(Some(*at));
+    let mut post = Post::with_required_approvals(*required_approvals).with_clock(clock.clone());
+
+    for (index, event) in events.iter().enumerate().skip(1) {
+        clock.freeze(Some(event.at));
+        apply(&mut post, &event.kind).map_err(|cause| EventError::Replay {
+            index,
+            cause: Box::new(cause),
+        })?;
+    }
+
+    Ok(post)
+}
+
+fn apply(post: &mut Post, kind: &EventKind) -> Result<(), EventError> {
+    match kind {
+        EventKind::Created { .. } => return Err(EventError::AlreadyCreated),
+        EventKind::TextAdded { actor, text } => post.add_text(actor, text)?,
+        EventKind::RevisionRestored { actor, revision } => post.restore(actor, *revision)?,
+        EventKind::TitleSet { actor, title } => post.set_title(actor, title)?,
+        EventKind::AuthorAdded { author } => post.add_author(author),
+        EventKind::TagAdded { tag } => post.add_tag(tag),
+        EventKind::TagRemoved { tag } => {
+            post.remove_tag(tag);
+        }
+        EventKind::ReviewRequested { actor } => post.request_review(actor)?,
+        EventKind::Approved { actor } => post.approve(actor)?,
+        EventKind::Scheduled { actor, publish_at } => post.schedule(actor, *publish_at)?,
+        EventKind::Rejected { actor } => post.reject(actor)?,
+        EventKind::Archived { actor } => post.archive(actor)?,
+        EventKind::Unarchived { actor } => post.unarchive(actor)?,
+        EventKind::Unpublished { actor } => post.unpublish(actor)?,
+        EventKind::Deleted { actor } => post.delete(actor)?,
+        EventKind::Commented {
+            author,
+            range,
+            text,
+            blocking,
+        } => {
+            post.comment(author, range.clone(), text, *blocking)?;
+        }
+        EventKind::Replied {
+            thread,
+            author,
+            text,
+        } => post.reply(*thread, author, text)?,
+        EventKind::Resolved { thread, actor } => post.resolve(*thread, actor)?,
+        EventKind::Unresolved { thread, actor } => post.unresolve(*thread, actor)?,
+    }
+    Ok(())
+}
+
+/// Keeps the events of every post in its own `<id>.jsonl` file inside a
+/// directory, one JSON object per line. Events are only ever appended.
+///
+/// Writers take an exclusive lock on the file for as long as they write to
+/// it, so several stores, even in different processes, can share a
+/// directory: [`JsonlEventStore::save`] checks for conflicts and appends
+/// under the same lock. Readers don't lock, and skip an event that is still
+/// being written.
+#[derive(Debug)]
+pub struct JsonlEventStore {
+    dir: PathBuf,
+}
+
+impl JsonlEventStore {
+    /// Opens the store in `dir`, creating the directory if needed.
+    pub fn open(dir: impl Into<PathBuf>) -> Result<JsonlEventStore, RepositoryError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(JsonlEventStore { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.jsonl", id))
+    }
+
+    /// Opens the log of post `id` for appending, creating it if needed, and
+    /// locks it until the file is dropped.
+    fn lock(&self, id: &str) -> Result<File, RepositoryError> {
+        check_id(id)?;
+
+        let file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(self.path(id))?;
+        file.lock()?;
+        Ok(file)
+    }
+
+    /// Appends `events` to the log of post `id`, creating it if needed.
+    pub fn append(&mut self, id: &str, events: &[Event]) -> Result<(), RepositoryError> {
+        let mut file = self.lock(id)?;
+        write_events(&mut file, events)
+    }
+
+    /// Appends the events of `post` that aren't in the log of `id` yet.
+    ///
+    /// The log has to be the start of the post's history, so a post can only
+    /// be saved on top of the log it was loaded from. Anything else, such as
+    /// a post that was saved by someone else in the meantime, is a
+    /// [`RepositoryError::Conflict`] and leaves the log alone.
+    pub fn save(&mut self, id: &str, post: &EventSourcedPost) -> Result<(), RepositoryError> {
+        // Nobody else may append between the check and our own append.
+        let mut file = self.lock(id)?;
+
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        let stored = parse_log(&contents)?;
+        let events = post.events();
+
+        if !events.starts_with(&stored) {
+            return Err(RepositoryError::Conflict(id.to_string()));
+        }
+        write_events(&mut file, &events[stored.len()..])
+    }
+
+    /// Reads the log of post `id`; empty if there is none.
+    ///
+    /// A last line without a line break is an event still being written, or
+    /// the remains of an append that was cut short, and is skipped. Any other
+    /// line that isn't an event is an error.
+    pub fn load(&self, id: &str) -> Result<Vec<Event>, RepositoryError> {
+        check_id(id)?;
+
+        match fs::read_to_string(self.path(id)) {
+            Ok(contents) => parse_log(&contents),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// The ids of all stored posts, in sorted order.
+    pub fn ids(&self) -> Result<Vec<String>, RepositoryError> {
+        ids_in(&self.dir, "jsonl")
+    }
+}
+
+fn parse_log(contents: &str) -> Result<Vec<Event>, RepositoryError> {
+    // Only lines that have been finished count.
+    let complete = contents.rfind('\n').map_or("", |end| &contents[..end]);
+
+    complete
+        .lines()
+        .map(|line| serde_json::from_str(line).map_err(RepositoryError::from))
+        .collect()
+}
+
+/// Appends `events` to a log that the caller has locked.
+fn write_events(file: &mut File, events: &[Event]) -> Result<(), RepositoryError> {
+    let mut lines = Vec::new();
+    for event in events {
+        serde_json::to_writer(&mut lines, event)?;
+        lines.push(b'\n');
+    }
+
+    // If an earlier append was cut short, its remains would swallow the first
+    // new event. Nobody else is writing while we hold the lock, so they can
+    // be cut off.
+    if !ends_with_newline(file)? {
+        let mut contents = Vec::new();
+        file.seek(SeekFrom::Start(0))?;
+        file.read_to_end(&mut contents)?;
+        let end = contents
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |i| i + 1);
+        file.set_len(end as u64)?;
+    }
+
+    file.write_all(&lines)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+fn ends_with_newline(file: &mut File) -> io::Result<bool> {
+    if file.metadata()?.len() == 0 {
+        return Ok(true);
+    }
+
+    let mut last = [0];
+    file.seek(SeekFrom::End(-1))?;
+    file.read_exact(&mut last)?;
+    Ok(last[0] == b'\n')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{clock::ManualClock, error::WorkflowError, Status};
+    use std::{sync::Barrier, thread, time::Duration};
+
+    fn at(secs: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    /// A post written at 0s, sent for review at 10s, and approved at 20s.
+    fn published() -> (EventSourcedPost, Arc<ManualClock>) {
+        let clock = Arc::new(ManualClock::new(at(0)));
+        let mut post = EventSourcedPost::with_clock(1, clock.clone());
+
+        post.execute(EventKind::AuthorAdded {
+            author: "author".into(),
+        })
+        .unwrap();
+        post.execute(EventKind::TextAdded {
+            actor: Actor::author("author"),
+            text: "I ate a salad".into(),
+        })
+        .unwrap();
+
+        clock.set(at(10));
+        post.execute(EventKind::ReviewRequested {
+            actor: Actor::author("author"),
+        })
+        .unwrap();
+        post.execute(EventKind::Commented {
+            author: "alice".into(),
+            range: 8..13,
+            text: "Which salad?".into(),
+            blocking: false,
+        })
+        .unwrap();
+
+        clock.set(at(20));
+        post.execute(EventKind::Approved {
+            actor: Actor::editor("alice"),
+        })
+        .unwrap();
+
+        (post, clock)
+    }
+
+    #[test]
+    fn replays_to_the_same_post() {
+        let (post, _) = published();
+        let replayed = EventSourcedPost::replay(post.events().to_vec()).unwrap();
+
+        assert_eq!(Status::Published, replayed.post().status());
+        assert_eq!("I ate a salad", replayed.post().content());
+        assert_eq!(post.post().audit_log(), replayed.post().audit_log());
+        assert_eq!(post.post().revisions(), replayed.post().revisions());
+        assert_eq!(post.post().threads(), replayed.post().threads());
+        assert_eq!(at(0), replayed.post().created_at());
+    }
+
+    #[test]
+    fn travels_in_time() {
+        let (post, _) = published();
+
+        let draft = post.as_of(at(5)).unwrap();
+        assert_eq!(Status::Draft, draft.status());
+        assert_eq!(1, draft.revisions().len());
+
+        let pending = post.as_of(at(19)).unwrap();
+        assert_eq!(Status::PendingReview, pending.status());
+        assert_eq!("", pending.content());
+        assert_eq!(1, pending.threads().len());
+
+        assert_eq!("I ate a salad", post.as_of(at(20)).unwrap().content());
+    }
+
+    #[test]
+    fn failed_changes_are_not_recorded() {
+        let (mut post, _) = published();
+        let count = post.events().len();
+
+        let error = post
+            .execute(EventKind::Rejected {
+                actor: Actor::editor("alice"),
+            })
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            EventError::Workflow(WorkflowError::Transition(_))
+        ));
+        assert_eq!(
+            Err(EventError::AlreadyCreated),
+            post.execute(EventKind::Created {
+                required_approvals: 1
+            })
+        );
+        assert_eq!(count, post.events().len());
+    }
+
+    #[test]
+    fn rejects_invalid_logs() {
+        assert_eq!(
+            Err(EventError::NotCreated),
+            EventSourcedPost::replay(Vec::new()).map(|_| ())
+        );
+
+        let (post, _) = published();
+        let mut events = post.events().to_vec();
+        // Approving before the review was requested.
+        events.swap(3, 5);
+
+        assert!(matches!(
+            EventSourcedPost::replay(events),
+            Err(EventError::Replay { index: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn stores_events_as_json_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = JsonlEventStore::open(dir.path()).unwrap();
+        let (mut post, clock) = published();
+
+        store.save("salad", &post).unwrap();
+        clock.set(at(30));
+        post.execute(EventKind::Archived {
+            actor: Actor::editor("alice"),
+        })
+        .unwrap();
+        store.save("salad", &post).unwrap();
+
+        let contents = fs::read_to_string(dir.path().join("salad.jsonl")).unwrap();
+        assert_eq!(7, contents.lines().count());
+        assert!(contents
+            .lines()
+            .nth(6)
+            .unwrap()
+            .contains("\"type\":\"archived\""));
+
+        // A torn write at the end of the log is ignored.
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(dir.path().join("salad.jsonl"))
+            .unwrap();
+        file.write_all(b"{\"at\":").unwrap();
+
+        let events = store.load("salad").unwrap();
+        assert_eq!(post.events(), &events[..]);
+        let replayed = EventSourcedPost::replay(events).unwrap();
+        assert_eq!(Status::Archived, replayed.post().status());
+
+        clock.set(at(40));
+        post.execute(EventKind::Unarchived {
+            actor: Actor::editor("alice"),
+        })
+        .unwrap();
+        store.save("salad", &post).unwrap();
+        assert_eq!(post.events(), &store.load("salad").unwrap()[..]);
+        // The remains were cut off before appending.
+        let contents = fs::read_to_string(dir.path().join("salad.jsonl")).unwrap();
+        assert_eq!(8, contents.lines().count());
+        assert!(!contents.contains("{\"at\":\n"));
+
+        // Anything else that isn't an event is an error, including a torn
+        // line that something was written after.
+        for broken in ["{\"at\": 1}\n", "{\"at\":\n{\"at\":"] {
+            fs::write(dir.path().join("broken.jsonl"), broken).unwrap();
+            assert!(matches!(
+                store.load("broken"),
+                Err(RepositoryError::Format(_))
+            ));
+        }
+        fs::remove_file(dir.path().join("broken.jsonl")).unwrap();
+        fs::write(dir.path().join("notes.txt"), "").unwrap();
+
+        assert_eq!(vec!["salad"], store.ids().unwrap());
+        assert!(store.load("other").unwrap().is_empty());
+    }
+
+    #[test]
+    fn saves_only_on_top_of_the_stored_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = JsonlEventStore::open(dir.path()).unwrap();
+        let (mut post, clock) = published();
+        store.save("salad", &post).unwrap();
+
+        // Someone else loads the post and archives it first.
+        let mut theirs = EventSourcedPost::replay(store.load("salad").unwrap()).unwrap();
+        theirs
+            .execute(EventKind::Archived {
+                actor: Actor::editor("bob"),
+            })
+            .unwrap();
+        store.save("salad", &theirs).unwrap();
+
+        // Our copy is now behind the log...
+        let stored = store.load("salad").unwrap();
+        assert!(matches!(
+            store.save("salad", &post),
+            Err(RepositoryError::Conflict(id)) if id == "salad"
+        ));
+
+        // ...and once it has changes of its own, it has diverged from it.
+        clock.set(at(30));
+        post.execute(EventKind::Unpublished {
+            actor: Actor::editor("alice"),
+        })
+        .unwrap();
+        assert_eq!(stored.len(), post.events().len());
+        assert!(matches!(
+            store.save("salad", &post),
+            Err(RepositoryError::Conflict(_))
+        ));
+
+        // A post with a different history can't be saved over it either.
+        let (other, _) = published();
+        let mut other = EventSourcedPost::replay(other.events()[..2].to_vec()).unwrap();
+        other
+            .execute(EventKind::TagAdded {
+                tag: String::from("lunch"),
+            })
+            .unwrap();
+        assert!(matches!(
+            store.save("salad", &other),
+            Err(RepositoryError::Conflict(_))
+        ));
+
+        assert_eq!(stored, store.load("salad").unwrap());
+    }
+
+    #[test]
+    fn only_one_of_several_writers_saves_on_top_of_a_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let (post, _) = published();
+        JsonlEventStore::open(dir.path())
+            .unwrap()
+            .save("salad", &post)
+            .unwrap();
+
+        // Every writer loads the same log and adds a change of its own before
+        // any of them saves.
+        let loaded = Arc::new(Barrier::new(32));
+        let saved: Vec<bool> = (0..32)
+            .map(|n| {
+                let dir = dir.path().to_path_buf();
+                let loaded = loaded.clone();
+                thread::spawn(move || {
+                    let mut store = JsonlEventStore::open(dir).unwrap();
+                    let mut post = EventSourcedPost::replay(store.load("salad").unwrap()).unwrap();
+                    post.execute(EventKind::TagAdded {
+                        tag: format!("tag{}", n),
+                    })
+                    .unwrap();
+                    loaded.wait();
+
+                    match store.save("salad", &post) {
+                        Ok(()) => true,
+                        Err(RepositoryError::Conflict(_)) => false,
+                        Err(e) => panic!("{}", e),
+                    }
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|writer| writer.join().unwrap())
+            .collect();
+
+        assert_eq!(1, saved.iter().filter(|&&saved| saved).count());
+        let store = JsonlEventStore::open(dir.path()).unwrap();
+        assert_eq!(post.events().len() + 1, store.load("salad").unwrap().len());
+    }
+}
@@ -1,88 +1,1185 @@
+use std::{fmt, ops::Range, sync::Arc, time::SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+pub mod api;
+pub mod audit;
+pub mod clock;
+pub mod collection;
+pub mod error;
+pub mod events;
+pub mod lifecycle;
+pub mod machine;
+pub mod markdown;
+pub mod metadata;
+pub mod policy;
+pub mod repository;
+pub mod review;
+pub mod revision;
+pub mod search;
+pub mod site;
+pub mod typestate;
+
+use audit::AuditEntry;
+use clock::{Clock, SystemClock};
+use error::{EditError, PermissionError, ReviewError, TransitionError, WorkflowError};
+use lifecycle::Context;
+use metadata::Metadata;
+use policy::{Actor, DefaultPolicy, Policy};
+use review::{Comment, Thread};
+use revision::{DiffLine, Revision};
+
+/// The state a post is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Draft,
+    PendingReview,
+    /// Approved, but waiting for its publish time to come.
+    Scheduled,
+    Published,
+    /// Taken down from the blog but kept, and can be put back.
+    Archived,
+    /// Taken back to be reworked; behaves like a draft.
+    Unpublished,
+    /// A tombstone: the post is gone for good.
+    Deleted,
+}
+
+impl Status {
+    pub const ALL: [Status; 7] = [
+        Status::Draft,
+        Status::PendingReview,
+        Status::Scheduled,
+        Status::Published,
+        Status::Archived,
+        Status::Unpublished,
+        Status::Deleted,
+    ];
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Status::Draft => "draft",
+            Status::PendingReview => "pending review",
+            Status::Scheduled => "scheduled",
+            Status::Published => "published",
+            Status::Archived => "archived",
+            Status::Unpublished => "unpublished",
+            Status::Deleted => "deleted",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Something a user can try to do to a post.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    RequestReview,
+    Approve,
+    /// Approve, and publish at a given time instead of right away.
+    Schedule,
+    Reject,
+    /// A scheduled post reaching its publish time.
+    Publish,
+    Archive,
+    Unarchive,
+    Unpublish,
+    Delete,
+}
+
+impl Action {
+    pub const ALL: [Action; 9] = [
+        Action::RequestReview,
+        Action::Approve,
+        Action::Schedule,
+        Action::Reject,
+        Action::Publish,
+        Action::Archive,
+        Action::Unarchive,
+        Action::Unpublish,
+        Action::Delete,
+    ];
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Action::RequestReview => "request review of",
+            Action::Approve => "approve",
+            Action::Schedule => "schedule",
+            Action::Reject => "reject",
+            Action::Publish => "publish",
+            Action::Archive => "archive",
+            Action::Unarchive => "unarchive",
+            Action::Unpublish => "unpublish",
+            Action::Delete => "delete",
+        };
+        f.write_str(name)
+    }
+}
+
 pub struct Post {
-    state: Option<Box<dyn State>>,
+    state: Status,
+    /// The approvals and publish time the lifecycle keeps track of.
+    lifecycle: Context,
     content: String,
+    audit_log: Vec<AuditEntry>,
+    revisions: Vec<Revision>,
+    threads: Vec<Thread>,
+    metadata: Metadata,
+    clock: Arc<dyn Clock>,
+    policy: Arc<dyn Policy>,
+}
+
+impl Default for Post {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Post {
     pub fn new() -> Post {
-        Post {
-            state: Some(Box::new(Draft {})),
+        Post::with_required_approvals(1)
+    }
+
+    /// Creates a post that needs `required_approvals` different reviewers to
+    /// approve it before it is published. A post always needs at least one.
+    pub fn with_required_approvals(required_approvals: usize) -> Post {
+        Post::with_state(
+            Status::Draft,
+            String::new(),
+            Context::new(required_approvals),
+        )
+    }
+
+    fn with_state(state: Status, content: String, lifecycle: Context) -> Post {
+        let mut post = Post {
+            state,
+            lifecycle,
             content: String::new(),
+            audit_log: Vec::new(),
+            revisions: Vec::new(),
+            threads: Vec::new(),
+            metadata: Metadata::new(SystemClock.now()),
+            clock: Arc::new(SystemClock),
+            policy: Arc::new(DefaultPolicy),
+        };
+
+        if !content.is_empty() {
+            post.save_revision(content);
+        }
+        post
+    }
+
+    /// Makes the post take the current time from `clock`, e.g. a
+    /// [`ManualClock`](clock::ManualClock) in tests.
+    ///
+    /// A post that hasn't been edited yet counts as created at the current
+    /// time of `clock`.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Post {
+        if self.revisions.is_empty() {
+            self.metadata.created_at = clock.now();
+            self.metadata.updated_at = clock.now();
+        }
+        self.clock = clock;
+        self
+    }
+
+    /// Makes the post check every transition and edit against `policy`
+    /// instead of [`DefaultPolicy`].
+    pub fn with_policy(mut self, policy: Arc<dyn Policy>) -> Post {
+        self.policy = policy;
+        self
+    }
+
+    pub fn title(&self) -> &str {
+        &self.metadata.title
+    }
+
+    /// Changes the title. The title is reviewed along with the content, so
+    /// like the content it can only be changed by those the [`Policy`] lets
+    /// edit the post, and only in the [editable](lifecycle::EDITABLE) states.
+    pub fn set_title(&mut self, editor: &Actor, title: &str) -> Result<(), EditError> {
+        self.check_editable(editor)?;
+
+        self.metadata.title = title.to_string();
+        self.touch();
+        Ok(())
+    }
+
+    /// The URL slug made from the title; see [`metadata::slugify`]. A
+    /// [`Collection`](collection::Collection) may add a number to keep it
+    /// unique.
+    pub fn slug(&self) -> String {
+        metadata::slugify(&self.metadata.title)
+    }
+
+    pub fn authors(&self) -> &[String] {
+        &self.metadata.authors
+    }
+
+    /// Adds `author` to the end of the author list, unless they are on it
+    /// already.
+    pub fn add_author(&mut self, author: &str) {
+        if !self.metadata.authors.iter().any(|a| a == author) {
+            self.metadata.authors.push(author.to_string());
+            self.touch();
+        }
+    }
+
+    /// The tags of the post, in lowercase and sorted.
+    pub fn tags(&self) -> Vec<&str> {
+        self.metadata.tags.iter().map(String::as_str).collect()
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.metadata.tags.contains(&tag.to_lowercase())
+    }
+
+    /// Tags the post. Unlike the title, tags can be changed in any state.
+    pub fn add_tag(&mut self, tag: &str) {
+        if self.metadata.tags.insert(tag.to_lowercase()) {
+            self.touch();
+        }
+    }
+
+    /// Removes a tag, returning whether the post had it.
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        let removed = self.metadata.tags.remove(&tag.to_lowercase());
+        if removed {
+            self.touch();
+        }
+        removed
+    }
+
+    pub fn created_at(&self) -> SystemTime {
+        self.metadata.created_at
+    }
+
+    /// When the content or metadata last changed.
+    pub fn updated_at(&self) -> SystemTime {
+        self.metadata.updated_at
+    }
+
+    fn touch(&mut self) {
+        self.metadata.updated_at = self.clock.now();
+    }
+
+    /// Appends `text` to the content. Like every edit, this is checked
+    /// against the post's [`Policy`] first, and only drafts and unpublished
+    /// posts can be edited.
+    pub fn add_text(&mut self, editor: &Actor, text: &str) -> Result<(), EditError> {
+        self.check_editable(editor)?;
+
+        let content = format!("{}{}", self.content, text);
+        self.save_revision(content);
+        Ok(())
+    }
+
+    /// Every edit made to the content, oldest first.
+    pub fn revisions(&self) -> &[Revision] {
+        &self.revisions
+    }
+
+    pub fn revision(&self, number: usize) -> Option<&Revision> {
+        // Revisions are numbered from 1, so revision n is at index n - 1.
+        number
+            .checked_sub(1)
+            .and_then(|index| self.revisions.get(index))
+    }
+
+    /// Compares two revisions line by line.
+    pub fn diff(&self, from: usize, to: usize) -> Result<Vec<DiffLine<'_>>, EditError> {
+        let from = self
+            .revision(from)
+            .ok_or(EditError::UnknownRevision(from))?;
+        let to = self.revision(to).ok_or(EditError::UnknownRevision(to))?;
+
+        Ok(revision::diff(&from.content, &to.content))
+    }
+
+    /// Makes the content of an earlier revision the current content. This is
+    /// an edit like any other: it is only allowed in the editable states and
+    /// adds a new revision, so the history is never rewritten.
+    pub fn restore(&mut self, editor: &Actor, number: usize) -> Result<(), EditError> {
+        self.check_editable(editor)?;
+
+        let content = self
+            .revision(number)
+            .ok_or(EditError::UnknownRevision(number))?
+            .content
+            .clone();
+        self.save_revision(content);
+        Ok(())
+    }
+
+    fn check_editable(&self, editor: &Actor) -> Result<(), EditError> {
+        if !self.policy.may_edit(editor, self) {
+            Err(EditError::NotAllowed(editor.clone()))
+        } else if self.can_edit() {
+            Ok(())
+        } else {
+            Err(EditError::NotEditable(self.status()))
         }
     }
 
-    pub fn add_text(&mut self, text: &str) {
-        self.content.push_str(text);
+    fn save_revision(&mut self, content: String) {
+        self.revisions.push(Revision {
+            number: self.revisions.len() + 1,
+            content: content.clone(),
+            at: self.clock.now(),
+        });
+        self.content = content;
+        self.touch();
     }
 
+    /// The content, if the post is published. The embargo on a scheduled
+    /// post is checked whenever the content is asked for, rather than by a
+    /// background timer, so that the post never shows up late or early.
     pub fn content(&self) -> &str {
-        self.state.as_ref().unwrap().content(self)
+        match self.status() {
+            Status::Published => &self.content,
+            _ => "",
+        }
+    }
+
+    /// Renders the content, which is written in Markdown, as sanitized HTML.
+    ///
+    /// Unlike [`Post::content`] this works in every state, so that authors
+    /// and reviewers can preview a post before it is published.
+    pub fn render_html(&self) -> String {
+        markdown::render_html(&self.content)
     }
 
-    pub fn request_review(&mut self) {
-        // To consume the old state, the request_review method needs to take
-        // ownership of the state value. This is where the Option in the state
-        // field of Post comes in: we call the take method to take the Some
-        // value out of the state field and leave a None in its place, because
-        // Rust doesn’t let us have unpopulated fields in structs.
-        if let Some(s) = self.state.take() {
-            self.state = Some(s.request_review())
+    /// The state the post is in. A scheduled post counts as published as
+    /// soon as its publish time has come.
+    pub fn status(&self) -> Status {
+        if self.is_due() {
+            Status::Published
+        } else {
+            self.state
         }
     }
 
-    pub fn approve(&mut self) {
-        if let Some(s) = self.state.take() {
-            self.state = Some(s.approve())
+    /// When a scheduled post will be (or was) published, if it has a publish
+    /// time.
+    pub fn publish_at(&self) -> Option<SystemTime> {
+        self.lifecycle.publish_at
+    }
+
+    // The lifecycle decides whether the content may change, but the Post does
+    // the changing, so that every edit ends up in the revision history.
+    fn can_edit(&self) -> bool {
+        lifecycle::can_edit(self.state)
+    }
+
+    fn in_review(&self) -> bool {
+        lifecycle::in_review(self.state)
+    }
+
+    /// Whether the post is scheduled and its publish time has come.
+    fn is_due(&self) -> bool {
+        self.state == Status::Scheduled
+            && self
+                .lifecycle
+                .publish_at
+                .is_some_and(|publish_at| self.clock.now() >= publish_at)
+    }
+
+    /// Asks for the post to be reviewed. Like every other transition, this
+    /// is checked against the post's [`Policy`] first.
+    pub fn request_review(&mut self, author: &Actor) -> Result<(), WorkflowError> {
+        self.transition(Action::RequestReview, author, |_| {})
+    }
+
+    /// Records an approval from `reviewer`. Approving twice as the same
+    /// reviewer counts once; the post is published once enough different
+    /// reviewers have approved it.
+    pub fn approve(&mut self, reviewer: &Actor) -> Result<(), WorkflowError> {
+        self.transition(Action::Approve, reviewer, |c| {
+            c.approvals.insert(reviewer.name.clone());
+        })
+    }
+
+    /// Approves the post like [`Post::approve`], but once it has enough
+    /// approvals it is held back as [`Status::Scheduled`] until `publish_at`
+    /// instead of being published right away.
+    pub fn schedule(
+        &mut self,
+        reviewer: &Actor,
+        publish_at: SystemTime,
+    ) -> Result<(), WorkflowError> {
+        self.transition(Action::Schedule, reviewer, |c| {
+            c.approvals.insert(reviewer.name.clone());
+            c.publish_at = Some(publish_at);
+        })
+    }
+
+    /// Sends a post under review back to draft, discarding the approvals it
+    /// has collected so far. A scheduled post that hasn't been published yet
+    /// can be rejected too, which cancels its publication.
+    pub fn reject(&mut self, reviewer: &Actor) -> Result<(), WorkflowError> {
+        self.transition(Action::Reject, reviewer, |_| {})
+    }
+
+    /// Takes a published post off the blog without losing it; see
+    /// [`Post::unarchive`].
+    pub fn archive(&mut self, actor: &Actor) -> Result<(), WorkflowError> {
+        self.transition(Action::Archive, actor, |_| {})
+    }
+
+    /// Puts an archived post back on the blog.
+    pub fn unarchive(&mut self, actor: &Actor) -> Result<(), WorkflowError> {
+        self.transition(Action::Unarchive, actor, |_| {})
+    }
+
+    /// Takes a published or archived post back so it can be edited and
+    /// reviewed again. Its revisions and audit log are kept.
+    pub fn unpublish(&mut self, actor: &Actor) -> Result<(), WorkflowError> {
+        self.transition(Action::Unpublish, actor, |_| {})
+    }
+
+    /// Soft-deletes the post: it stays around as a tombstone, with its
+    /// history, but can't be shown, edited or changed any more.
+    pub fn delete(&mut self, actor: &Actor) -> Result<(), WorkflowError> {
+        self.transition(Action::Delete, actor, |_| {})
+    }
+
+    /// The reviewers who have approved the post in its current review.
+    pub fn approvals(&self) -> Vec<&str> {
+        self.lifecycle
+            .approvals
+            .iter()
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Every successful transition of this post, oldest first.
+    pub fn audit_log(&self) -> &[AuditEntry] {
+        &self.audit_log
+    }
+
+    /// Starts a review thread about the bytes in `range` of the content and
+    /// returns its id. Threads can only be started while the post is pending
+    /// review. While a `blocking` thread is unresolved, the post can't be
+    /// approved.
+    pub fn comment(
+        &mut self,
+        author: &str,
+        range: Range<usize>,
+        text: &str,
+        blocking: bool,
+    ) -> Result<usize, ReviewError> {
+        if !self.in_review() {
+            return Err(ReviewError::NotInReview(self.status()));
         }
+
+        let Some(quote) = self.content.get(range.clone()) else {
+            return Err(ReviewError::InvalidRange(range));
+        };
+
+        let id = self.threads.len() + 1;
+        self.threads.push(Thread {
+            id,
+            quote: quote.to_string(),
+            range,
+            revision: self.revisions.len(),
+            blocking,
+            resolved_by: None,
+            comments: vec![Comment {
+                author: author.to_string(),
+                text: text.to_string(),
+                at: self.clock.now(),
+            }],
+        });
+        Ok(id)
     }
-}
 
-trait State {
-    fn request_review(self: Box<Self>) -> Box<dyn State>;
-    fn approve(self: Box<Self>) -> Box<dyn State>;
+    /// Adds a comment to a thread. Threads can be replied to, resolved and
+    /// unresolved while the post is pending review, and while it is back in
+    /// draft so that its authors can answer the review.
+    pub fn reply(&mut self, thread: usize, author: &str, text: &str) -> Result<(), ReviewError> {
+        let at = self.clock.now();
+        self.open_thread(thread)?.comments.push(Comment {
+            author: author.to_string(),
+            text: text.to_string(),
+            at,
+        });
+        Ok(())
+    }
 
-    // We add a default implementation for the content method that returns an
-    // empty string slice. That means we don’t need to implement content on the
-    // Draft and PendingReview structs.
-    fn content<'a>(&self, post: &'a Post) -> &'a str {
-        ""
+    /// Marks a thread as resolved, which stops a blocking thread from holding
+    /// up approval. Like every other change, this is checked against the
+    /// post's [`Policy`] first.
+    pub fn resolve(&mut self, thread: usize, actor: &Actor) -> Result<(), ReviewError> {
+        self.check_resolver(thread, actor)?;
+        self.open_thread(thread)?.resolved_by = Some(actor.name.clone());
+        Ok(())
     }
-}
 
-struct Draft {}
+    /// Opens a resolved thread again. The same people may do this as may
+    /// resolve it.
+    pub fn unresolve(&mut self, thread: usize, actor: &Actor) -> Result<(), ReviewError> {
+        self.check_resolver(thread, actor)?;
+        self.open_thread(thread)?.resolved_by = None;
+        Ok(())
+    }
 
-impl State for Draft {
-    fn request_review(self: Box<Self>) -> Box<dyn State> {
-        Box::new(PendingReview {})
+    /// Every review thread, in the order they were started.
+    pub fn threads(&self) -> &[Thread] {
+        &self.threads
     }
-    fn approve(self: Box<Self>) -> Box<dyn State> {
-        self
+
+    pub fn thread(&self, id: usize) -> Option<&Thread> {
+        id.checked_sub(1).and_then(|index| self.threads.get(index))
     }
-}
 
-struct PendingReview {}
+    fn check_resolver(&self, id: usize, actor: &Actor) -> Result<(), ReviewError> {
+        let thread = self.thread(id).ok_or(ReviewError::UnknownThread(id))?;
 
-impl State for PendingReview {
-    fn request_review(self: Box<Self>) -> Box<dyn State> {
-        self
+        if self.policy.may_resolve(actor, thread, self) {
+            Ok(())
+        } else {
+            Err(ReviewError::NotAllowed {
+                actor: actor.clone(),
+                thread: id,
+            })
+        }
     }
-    fn approve(self: Box<Self>) -> Box<dyn State> {
-        Box::new(Published {})
+
+    fn open_thread(&mut self, id: usize) -> Result<&mut Thread, ReviewError> {
+        if !self.in_review() && !self.can_edit() {
+            return Err(ReviewError::NotInReview(self.status()));
+        }
+
+        id.checked_sub(1)
+            .and_then(|index| self.threads.get_mut(index))
+            .ok_or(ReviewError::UnknownThread(id))
+    }
+
+    /// Fires `action` through the [lifecycle](lifecycle::machine), after
+    /// `prepare` has recorded in the context what the guards need to know.
+    fn transition(
+        &mut self,
+        action: Action,
+        actor: &Actor,
+        prepare: impl FnOnce(&mut Context),
+    ) -> Result<(), WorkflowError> {
+        self.publish_if_due();
+
+        if !self.policy.permits(actor, action, self) {
+            return Err(PermissionError {
+                actor: actor.clone(),
+                action,
+            }
+            .into());
+        }
+
+        let from = self.state;
+        let machine = lifecycle::machine();
+        if !machine.handles(from, action) {
+            return Err(TransitionError {
+                status: from,
+                action,
+            }
+            .into());
+        }
+
+        // Work on a copy, so that nothing changes if no transition applies.
+        let mut context = self.lifecycle.clone();
+        context.blocking_threads = self
+            .threads
+            .iter()
+            .filter(|thread| thread.is_blocking())
+            .map(|thread| thread.id)
+            .collect();
+        prepare(&mut context);
+
+        let to = match machine.fire(from, action, &mut context) {
+            Ok(to) => to,
+            // The machine handles the action, so its guards turned it down,
+            // and only blocking threads can make every guard fail.
+            Err(_) if !context.blocking_threads.is_empty() => {
+                return Err(WorkflowError::Blocked {
+                    action,
+                    threads: context.blocking_threads,
+                })
+            }
+            Err(e) => return Err(TransitionError::from(e).into()),
+        };
+
+        self.audit_log.push(AuditEntry {
+            action,
+            from,
+            to,
+            actor: actor.name.clone(),
+            at: self.clock.now(),
+        });
+        self.state = to;
+        self.lifecycle = context;
+        Ok(())
+    }
+
+    /// Moves a scheduled post whose time has come on to published, so that
+    /// the audit log shows when the post went live.
+    fn publish_if_due(&mut self) {
+        let Some(publish_at) = self.lifecycle.publish_at.filter(|_| self.is_due()) else {
+            return;
+        };
+
+        if let Ok(to) = lifecycle::machine().fire(self.state, Action::Publish, &mut self.lifecycle)
+        {
+            self.audit_log.push(AuditEntry {
+                action: Action::Publish,
+                from: self.state,
+                to,
+                actor: String::from("scheduler"),
+                at: publish_at,
+            });
+            self.state = to;
+        }
     }
 }
 
-struct Published {}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use std::time::Duration;
 
-impl State for Published {
-    fn request_review(self: Box<Self>) -> Box<dyn State> {
-        self
+    fn post_in_review(required_approvals: usize) -> Post {
+        let mut post = Post::with_required_approvals(required_approvals);
+        post.add_author("author");
+        post.add_text(&Actor::author("author"), "I ate a salad for lunch today")
+            .unwrap();
+        post.request_review(&Actor::author("author")).unwrap();
+        post
     }
 
-    fn approve(self: Box<Self>) -> Box<dyn State> {
-        self
+    #[test]
+    fn single_approval_publishes_by_default() {
+        let mut post = Post::new();
+        post.add_author("author");
+        post.add_text(&Actor::author("author"), "I ate a salad for lunch today")
+            .unwrap();
+        post.request_review(&Actor::author("author")).unwrap();
+        post.approve(&Actor::editor("alice")).unwrap();
+
+        assert_eq!(Status::Published, post.status());
+        assert_eq!("I ate a salad for lunch today", post.content());
+    }
+
+    #[test]
+    fn requires_distinct_reviewers() {
+        let mut post = post_in_review(2);
+
+        post.approve(&Actor::editor("alice")).unwrap();
+        post.approve(&Actor::editor("alice")).unwrap();
+        assert_eq!("", post.content());
+        assert_eq!(vec!["alice"], post.approvals());
+
+        post.approve(&Actor::editor("bob")).unwrap();
+        assert_eq!("I ate a salad for lunch today", post.content());
+    }
+
+    #[test]
+    fn reject_returns_to_draft_and_resets_approvals() {
+        let mut post = post_in_review(2);
+
+        post.approve(&Actor::editor("alice")).unwrap();
+        post.reject(&Actor::editor("bob")).unwrap();
+        assert_eq!(Status::Draft, post.status());
+        assert!(post.approvals().is_empty());
+
+        post.request_review(&Actor::author("author")).unwrap();
+        post.approve(&Actor::editor("bob")).unwrap();
+        assert_eq!("", post.content());
+        post.approve(&Actor::editor("carol")).unwrap();
+        assert_eq!("I ate a salad for lunch today", post.content());
+    }
+
+    #[test]
+    fn invalid_transitions_are_errors() {
+        let mut post = Post::new();
+        post.add_author("author");
+
+        assert_eq!(
+            Err(WorkflowError::Transition(TransitionError {
+                status: Status::Draft,
+                action: Action::Approve
+            })),
+            post.approve(&Actor::editor("alice"))
+        );
+        assert!(post.reject(&Actor::editor("alice")).is_err());
+        assert_eq!(Status::Draft, post.status());
+
+        post.request_review(&Actor::author("author")).unwrap();
+        assert!(post.request_review(&Actor::author("author")).is_err());
+        post.approve(&Actor::editor("alice")).unwrap();
+
+        let error = post.reject(&Actor::editor("bob")).unwrap_err();
+        assert_eq!(
+            "cannot reject a post in the published state",
+            error.to_string()
+        );
+        assert_eq!(Status::Published, post.status());
+    }
+
+    #[test]
+    fn records_successful_transitions() {
+        let mut post = post_in_review(2);
+        post.approve(&Actor::editor("alice")).unwrap();
+        post.reject(&Actor::editor("bob")).unwrap();
+        post.approve(&Actor::editor("bob")).unwrap_err();
+        post.request_review(&Actor::author("author")).unwrap();
+        post.approve(&Actor::editor("carol")).unwrap();
+        post.approve(&Actor::editor("dave")).unwrap();
+
+        let log: Vec<_> = post
+            .audit_log()
+            .iter()
+            .map(|e| (e.action, e.from, e.to, e.actor.as_str()))
+            .collect();
+
+        use Action::*;
+        use Status::*;
+        assert_eq!(
+            vec![
+                (RequestReview, Draft, PendingReview, "author"),
+                (Approve, PendingReview, PendingReview, "alice"),
+                (Reject, PendingReview, Draft, "bob"),
+                (RequestReview, Draft, PendingReview, "author"),
+                (Approve, PendingReview, PendingReview, "carol"),
+                (Approve, PendingReview, Published, "dave"),
+            ],
+            log
+        );
+        assert!(post.audit_log().windows(2).all(|w| w[0].at <= w[1].at));
+    }
+
+    #[test]
+    fn only_drafts_can_be_edited() {
+        let mut post = post_in_review(1);
+
+        assert_eq!(
+            Err(EditError::NotEditable(Status::PendingReview)),
+            post.add_text(&Actor::author("author"), " and a sandwich")
+        );
+
+        post.reject(&Actor::editor("alice")).unwrap();
+        post.add_text(&Actor::author("author"), " and a sandwich")
+            .unwrap();
+        post.request_review(&Actor::author("author")).unwrap();
+        post.approve(&Actor::editor("alice")).unwrap();
+
+        let error = post.add_text(&Actor::author("author"), "!").unwrap_err();
+        assert_eq!(
+            "cannot edit a post in the published state",
+            error.to_string()
+        );
+        assert_eq!(
+            "I ate a salad for lunch today and a sandwich",
+            post.content()
+        );
+    }
+
+    #[test]
+    fn edits_are_checked_against_the_policy() {
+        let mut post = Post::new();
+        post.add_author("author");
+        post.add_text(&Actor::author("author"), "Hello").unwrap();
+
+        let mallory = Actor::author("mallory");
+        assert_eq!(
+            Err(EditError::NotAllowed(mallory.clone())),
+            post.add_text(&mallory, "!")
+        );
+        assert_eq!(
+            "author mallory may not edit this post",
+            post.set_title(&mallory, "Pwned").unwrap_err().to_string()
+        );
+        assert!(post.restore(&mallory, 1).is_err());
+        assert_eq!(1, post.revisions().len());
+        assert_eq!("", post.title());
+
+        // Editors may edit any post, but only in the editable states.
+        post.set_title(&Actor::editor("alice"), "Hello").unwrap();
+        post.request_review(&Actor::author("author")).unwrap();
+        assert_eq!(
+            Err(EditError::NotEditable(Status::PendingReview)),
+            post.add_text(&Actor::editor("alice"), "!")
+        );
+    }
+
+    #[test]
+    fn keeps_revisions() {
+        let mut post = Post::new();
+        post.add_author("author");
+        post.add_text(&Actor::author("author"), "Title\n").unwrap();
+        post.add_text(&Actor::author("author"), "First draft\n")
+            .unwrap();
+        post.restore(&Actor::author("author"), 1).unwrap();
+        post.add_text(&Actor::author("author"), "Second draft\n")
+            .unwrap();
+
+        let contents: Vec<_> = post
+            .revisions()
+            .iter()
+            .map(|r| (r.number, r.content.as_str()))
+            .collect();
+        assert_eq!(
+            vec![
+                (1, "Title\n"),
+                (2, "Title\nFirst draft\n"),
+                (3, "Title\n"),
+                (4, "Title\nSecond draft\n"),
+            ],
+            contents
+        );
+
+        assert_eq!(
+            vec![
+                DiffLine::Unchanged("Title"),
+                DiffLine::Removed("First draft"),
+                DiffLine::Added("Second draft"),
+            ],
+            post.diff(2, 4).unwrap()
+        );
+        assert_eq!(Err(EditError::UnknownRevision(0)), post.diff(0, 1));
+        assert_eq!(
+            Err(EditError::UnknownRevision(9)),
+            post.restore(&Actor::author("author"), 9)
+        );
+    }
+
+    #[test]
+    fn drafts_can_be_previewed() {
+        let mut post = Post::new();
+        post.add_author("author");
+        post.add_text(&Actor::author("author"), "I ate a *salad*")
+            .unwrap();
+
+        assert_eq!("", post.content());
+        assert_eq!("<p>I ate a <em>salad</em></p>\n", post.render_html());
     }
 
-    fn content<'a>(&self, post: &'a Post) -> &'a str {
-        &post.content
+    #[test]
+    fn scheduled_posts_stay_hidden_until_publish_time() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let clock = Arc::new(ManualClock::new(start));
+        let mut post = Post::with_required_approvals(2).with_clock(clock.clone());
+        post.add_author("author");
+        post.add_text(&Actor::author("author"), "Embargoed news")
+            .unwrap();
+        post.request_review(&Actor::author("author")).unwrap();
+
+        let publish_at = start + Duration::from_secs(3600);
+        post.schedule(&Actor::editor("alice"), publish_at).unwrap();
+        assert_eq!(Status::PendingReview, post.status());
+
+        // The second reviewer only approves; the schedule still applies.
+        post.approve(&Actor::editor("bob")).unwrap();
+        assert_eq!(Status::Scheduled, post.status());
+        assert_eq!(Some(publish_at), post.publish_at());
+        assert_eq!("", post.content());
+
+        clock.advance(Duration::from_secs(3599));
+        assert_eq!("", post.content());
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(Status::Published, post.status());
+        assert_eq!("Embargoed news", post.content());
+
+        // The next transition records when the post went live.
+        assert!(post.reject(&Actor::editor("carol")).is_err());
+        let last = post.audit_log().last().unwrap();
+        assert_eq!(
+            (Action::Publish, Status::Scheduled, publish_at),
+            (last.action, last.from, last.at)
+        );
+    }
+
+    #[test]
+    fn rejecting_a_scheduled_post_cancels_it() {
+        let start = SystemTime::UNIX_EPOCH;
+        let clock = Arc::new(ManualClock::new(start));
+        let mut post = Post::new().with_clock(clock.clone());
+        post.add_author("author");
+        post.add_text(&Actor::author("author"), "Embargoed news")
+            .unwrap();
+        post.request_review(&Actor::author("author")).unwrap();
+        post.schedule(&Actor::editor("alice"), start + Duration::from_secs(60))
+            .unwrap();
+
+        post.reject(&Actor::editor("alice")).unwrap();
+        clock.advance(Duration::from_secs(120));
+
+        assert_eq!(Status::Draft, post.status());
+        assert_eq!("", post.content());
+        assert_eq!(None, post.publish_at());
+    }
+
+    #[test]
+    fn checks_permissions_first() {
+        let mut post = post_in_review(1);
+        post.add_author("alice");
+
+        // Refused by the policy even though the state would allow it.
+        assert_eq!(
+            Err(WorkflowError::Permission(PermissionError {
+                actor: Actor::editor("alice"),
+                action: Action::Approve
+            })),
+            post.approve(&Actor::editor("alice"))
+        );
+        assert!(post.approve(&Actor::author("bob")).is_err());
+        assert_eq!(Status::PendingReview, post.status());
+        assert_eq!(1, post.audit_log().len());
+
+        struct Anyone;
+        impl Policy for Anyone {
+            fn permits(&self, _: &Actor, _: Action, _: &Post) -> bool {
+                true
+            }
+
+            fn may_edit(&self, _: &Actor, _: &Post) -> bool {
+                true
+            }
+
+            fn may_resolve(&self, _: &Actor, _: &Thread, _: &Post) -> bool {
+                true
+            }
+        }
+
+        let mut post = post.with_policy(Arc::new(Anyone));
+        post.approve(&Actor::author("alice")).unwrap();
+        assert_eq!(Status::Published, post.status());
+    }
+
+    #[test]
+    fn blocking_comments_hold_up_approval() {
+        let mut post = post_in_review(1);
+        let editor = Actor::editor("alice");
+
+        let salad = post.comment("alice", 8..13, "Which salad?", true).unwrap();
+        post.comment("bob", 0..1, "Nice", false).unwrap();
+        assert_eq!("salad", post.thread(salad).unwrap().quote);
+
+        assert_eq!(
+            Err(WorkflowError::Blocked {
+                action: Action::Approve,
+                threads: vec![salad]
+            }),
+            post.approve(&editor)
+        );
+        assert_eq!(Status::PendingReview, post.status());
+
+        // The author answers in draft, but the thread stays open until it is
+        // resolved.
+        post.reject(&editor).unwrap();
+        post.reply(salad, "author", "A caesar salad").unwrap();
+        post.request_review(&Actor::author("author")).unwrap();
+        assert!(post.approve(&editor).is_err());
+
+        // Only the reviewer who started the thread, or an editor, may
+        // resolve it; the author can't wave it away.
+        assert_eq!(
+            Err(ReviewError::NotAllowed {
+                actor: Actor::author("author"),
+                thread: salad
+            }),
+            post.resolve(salad, &Actor::author("author"))
+        );
+        assert!(post.approve(&editor).is_err());
+
+        post.resolve(salad, &editor).unwrap();
+        assert!(post.unresolve(salad, &Actor::author("author")).is_err());
+        post.unresolve(salad, &Actor::author("alice")).unwrap();
+        assert!(post.approve(&editor).is_err());
+        post.resolve(salad, &editor).unwrap();
+        post.approve(&editor).unwrap();
+
+        let thread = post.thread(salad).unwrap();
+        assert_eq!(2, thread.comments.len());
+        assert_eq!(Some("alice"), thread.resolved_by.as_deref());
+        assert_eq!(
+            Err(ReviewError::NotInReview(Status::Published)),
+            post.reply(salad, "bob", "Too late")
+        );
+    }
+
+    #[test]
+    fn comments_need_a_range_of_the_content() {
+        let mut post = Post::new();
+        post.add_author("author");
+        post.add_text(&Actor::author("author"), "Crème brûlée")
+            .unwrap();
+
+        assert_eq!(
+            Err(ReviewError::NotInReview(Status::Draft)),
+            post.comment("alice", 0..5, "Hm", false)
+        );
+
+        post.request_review(&Actor::author("author")).unwrap();
+        assert_eq!(
+            Err(ReviewError::InvalidRange(0..3)),
+            post.comment("alice", 0..3, "Splits the è", false)
+        );
+        assert_eq!(
+            Err(ReviewError::InvalidRange(10..20)),
+            post.comment("alice", 10..20, "Past the end", false)
+        );
+        assert_eq!(
+            Err(ReviewError::UnknownThread(1)),
+            post.resolve(1, &Actor::editor("alice"))
+        );
+    }
+
+    #[test]
+    fn keeps_metadata() {
+        let start = SystemTime::UNIX_EPOCH;
+        let clock = Arc::new(ManualClock::new(start));
+        let mut post = Post::new().with_clock(clock.clone());
+
+        clock.advance(Duration::from_secs(60));
+        post.set_title(&Actor::editor("carol"), "Crème brûlée")
+            .unwrap();
+        post.add_author("alice");
+        post.add_author("bob");
+        post.add_author("alice");
+        post.add_tag("Dessert");
+        post.add_tag("dessert");
+        post.add_tag("french");
+
+        assert_eq!("creme-brulee", post.slug());
+        assert_eq!(["alice", "bob"], post.authors());
+        assert_eq!(vec!["dessert", "french"], post.tags());
+        assert!(post.has_tag("DESSERT"));
+        assert_eq!(start, post.created_at());
+        assert_eq!(start + Duration::from_secs(60), post.updated_at());
+
+        // Transitions aren't updates, and only drafts can be retitled.
+        post.request_review(&Actor::author("alice")).unwrap();
+        clock.advance(Duration::from_secs(60));
+        post.approve(&Actor::editor("carol")).unwrap();
+        assert_eq!(start + Duration::from_secs(60), post.updated_at());
+        assert_eq!(
+            Err(EditError::NotEditable(Status::Published)),
+            post.set_title(&Actor::editor("carol"), "Flan")
+        );
+
+        assert!(post.remove_tag("French"));
+        assert!(!post.remove_tag("french"));
+        assert_eq!(start + Duration::from_secs(120), post.updated_at());
+    }
+
+    /// Drives a new post into `status` using only public actions.
+    fn post_in(status: Status) -> Post {
+        use Action::*;
+
+        let mut post = Post::new().with_clock(Arc::new(ManualClock::new(SystemTime::UNIX_EPOCH)));
+
+        post.add_author("author");
+        post.add_text(&Actor::author("author"), "Hello").unwrap();
+
+        let path: &[Action] = match status {
+            Status::Draft => &[],
+            Status::PendingReview => &[RequestReview],
+            Status::Scheduled => &[RequestReview, Schedule],
+            Status::Published => &[RequestReview, Approve],
+            Status::Archived => &[RequestReview, Approve, Archive],
+            Status::Unpublished => &[RequestReview, Approve, Unpublish],
+            Status::Deleted => &[Delete],
+        };
+
+        for &action in path {
+            perform(&mut post, action).unwrap();
+        }
+        assert_eq!(status, post.status());
+        post
+    }
+
+    fn perform(post: &mut Post, action: Action) -> Result<(), WorkflowError> {
+        match action {
+            Action::RequestReview => post.request_review(&Actor::author("author")),
+            Action::Approve => post.approve(&Actor::editor("editor")),
+            Action::Schedule => post.schedule(
+                &Actor::editor("editor"),
+                SystemTime::UNIX_EPOCH + Duration::from_secs(60),
+            ),
+            Action::Reject => post.reject(&Actor::editor("editor")),
+            Action::Archive => post.archive(&Actor::editor("editor")),
+            Action::Unarchive => post.unarchive(&Actor::editor("editor")),
+            Action::Unpublish => post.unpublish(&Actor::editor("editor")),
+            Action::Delete => post.delete(&Actor::admin("editor")),
+            Action::Publish => unreachable!("only the scheduler publishes"),
+        }
+    }
+
+    #[test]
+    fn follows_the_transition_table() {
+        for status in Status::ALL {
+            for action in Action::ALL {
+                if action == Action::Publish {
+                    continue;
+                }
+
+                let mut post = post_in(status);
+                let result = perform(&mut post, action);
+
+                match lifecycle::targets(status, action) {
+                    Some(targets) => {
+                        assert!(result.is_ok(), "{:?} in {:?}: {:?}", action, status, result);
+                        assert!(
+                            targets.contains(&post.status()),
+                            "{:?} in {:?} led to {:?}",
+                            action,
+                            status,
+                            post.status()
+                        );
+                    }
+                    None => {
+                        assert_eq!(
+                            Err(TransitionError { status, action }.into()),
+                            result,
+                            "{:?} in {:?}",
+                            action,
+                            status
+                        );
+                        assert_eq!(status, post.status());
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn lifecycle_after_publishing() {
+        let mut post = post_in(Status::Published);
+
+        post.archive(&Actor::editor("editor")).unwrap();
+        assert_eq!("", post.content());
+        post.unarchive(&Actor::editor("editor")).unwrap();
+        assert_eq!("Hello", post.content());
+
+        post.unpublish(&Actor::editor("editor")).unwrap();
+        assert_eq!("", post.content());
+        post.add_text(&Actor::author("author"), ", world").unwrap();
+        assert_eq!(2, post.revisions().len());
+
+        post.request_review(&Actor::author("author")).unwrap();
+        post.approve(&Actor::editor("editor")).unwrap();
+        assert_eq!("Hello, world", post.content());
+
+        post.delete(&Actor::admin("admin")).unwrap();
+        assert_eq!("", post.content());
+        assert_eq!(
+            Err(EditError::NotEditable(Status::Deleted)),
+            post.add_text(&Actor::author("author"), "!")
+        );
+        assert_eq!(8, post.audit_log().len());
     }
 }
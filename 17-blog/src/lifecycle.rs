@@ -0,0 +1,212 @@
+//! The lifecycle of a post, declared as a [`Machine`].
+//!
+//! [`Post`](crate::Post) fires every action through its state machine and asks
+//! [`can_edit`] and [`in_review`] before any edit or review comment, so this
+//! is the single place that says what is allowed in which state and where
+//! actions can lead. [`to_dot`] draws it.
+
+use std::{collections::BTreeSet, sync::OnceLock, time::SystemTime};
+
+use crate::{
+    machine::Machine,
+    Action::{self, *},
+    Status::{self, *},
+};
+
+/// What the guards and hooks of the lifecycle look at and update.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Context {
+    // A set, so that the same reviewer approving twice only counts once.
+    pub(crate) approvals: BTreeSet<String>,
+    // Set by the reviewers who schedule rather than approve; the latest one
+    // wins.
+    pub(crate) publish_at: Option<SystemTime>,
+    pub(crate) required_approvals: usize,
+    // The review threads that hold up approval, recorded by the post before
+    // every action.
+    pub(crate) blocking_threads: Vec<usize>,
+}
+
+impl Context {
+    pub(crate) fn new(required_approvals: usize) -> Context {
+        Context {
+            required_approvals: required_approvals.max(1),
+            ..Context::default()
+        }
+    }
+
+    fn approved(&self) -> bool {
+        self.approvals.len() >= self.required_approvals
+    }
+
+    fn unblocked(&self) -> bool {
+        self.blocking_threads.is_empty()
+    }
+}
+
+/// The post lifecycle. Approving and scheduling first record the reviewer
+/// (and publish time) in the context, and the guards then decide whether
+/// that was enough. Neither goes anywhere while a blocking review thread is
+/// unresolved.
+pub(crate) fn machine() -> &'static Machine<Status, Action, Context> {
+    static MACHINE: OnceLock<Machine<Status, Action, Context>> = OnceLock::new();
+
+    MACHINE.get_or_init(|| {
+        let mut machine = Machine::new(Draft)
+            .transition(Draft, RequestReview, PendingReview)
+            .transition(Draft, Delete, Deleted);
+
+        // An approval leaves the post pending review until it has enough of
+        // them.
+        for action in [Approve, Schedule] {
+            machine = machine
+                .guarded(
+                    PendingReview,
+                    action,
+                    Scheduled,
+                    "approved, publish time set, unblocked",
+                    |c: &Context| c.unblocked() && c.approved() && c.publish_at.is_some(),
+                )
+                .guarded(
+                    PendingReview,
+                    action,
+                    Published,
+                    "approved, unblocked",
+                    |c: &Context| c.unblocked() && c.approved(),
+                )
+                .guarded(
+                    PendingReview,
+                    action,
+                    PendingReview,
+                    "unblocked",
+                    Context::unblocked,
+                );
+        }
+
+        machine
+            .transition(PendingReview, Reject, Draft)
+            .transition(PendingReview, Delete, Deleted)
+            .transition(Scheduled, Reject, Draft)
+            .transition(Scheduled, Publish, Published)
+            .transition(Scheduled, Delete, Deleted)
+            .transition(Published, Archive, Archived)
+            .transition(Published, Unpublish, Unpublished)
+            .transition(Published, Delete, Deleted)
+            .transition(Archived, Unarchive, Published)
+            .transition(Archived, Unpublish, Unpublished)
+            .transition(Archived, Delete, Deleted)
+            .transition(Unpublished, RequestReview, PendingReview)
+            .transition(Unpublished, Delete, Deleted)
+            // Deleted is a tombstone: nothing leads out of it.
+            //
+            // Approvals only count within one review, and a publish time only
+            // until the post is published or sent back.
+            .on_enter(PendingReview, |c| {
+                c.approvals.clear();
+                c.publish_at = None;
+            })
+            .on_exit(PendingReview, |c| c.approvals.clear())
+            .on_enter(Draft, |c| c.publish_at = None)
+            .on_enter(Published, |c| c.publish_at = None)
+    })
+}
+
+/// The states in which the content and title of a post may change.
+pub const EDITABLE: &[Status] = &[Draft, Unpublished];
+
+/// The states in which reviewers can start new comment threads.
+pub const IN_REVIEW: &[Status] = &[PendingReview];
+
+pub fn can_edit(status: Status) -> bool {
+    EDITABLE.contains(&status)
+}
+
+pub fn in_review(status: Status) -> bool {
+    IN_REVIEW.contains(&status)
+}
+
+/// The states `action` may lead to from `from`, or `None` if the action isn't
+/// allowed there.
+pub fn targets(from: Status, action: Action) -> Option<Vec<Status>> {
+    let targets = machine().targets(from, action);
+    (!targets.is_empty()).then_some(targets)
+}
+
+/// Draws the lifecycle as a Graphviz DOT graph, e.g. for `dot -Tsvg`.
+pub fn to_dot() -> String {
+    machine().to_dot("post")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_state_except_deleted_can_be_left() {
+        for status in Status::ALL {
+            let leaves = Action::ALL
+                .iter()
+                .any(|&action| machine().handles(status, action));
+            assert_eq!(status != Deleted, leaves, "{:?}", status);
+        }
+    }
+
+    #[test]
+    fn edited_posts_can_be_sent_for_review() {
+        for &status in EDITABLE {
+            assert_eq!(Some(vec![PendingReview]), targets(status, RequestReview));
+        }
+        assert!(!can_edit(PendingReview));
+        assert!(in_review(PendingReview));
+    }
+
+    #[test]
+    fn approving_needs_enough_reviewers() {
+        let mut context = Context::new(2);
+
+        context.approvals.insert(String::from("alice"));
+        assert_eq!(
+            Ok(PendingReview),
+            machine().fire(PendingReview, Approve, &mut context)
+        );
+
+        context.approvals.insert(String::from("bob"));
+        context.publish_at = Some(SystemTime::UNIX_EPOCH);
+        assert_eq!(
+            Ok(Scheduled),
+            machine().fire(PendingReview, Schedule, &mut context)
+        );
+        assert!(context.approvals.is_empty());
+
+        assert_eq!(Ok(Draft), machine().fire(Scheduled, Reject, &mut context));
+        assert_eq!(None, context.publish_at);
+    }
+
+    #[test]
+    fn blocking_threads_hold_up_approval() {
+        let mut context = Context::new(1);
+        context.approvals.insert(String::from("alice"));
+        context.blocking_threads = vec![2];
+
+        for action in [Approve, Schedule] {
+            assert!(machine().fire(PendingReview, action, &mut context).is_err());
+        }
+        assert_eq!(
+            Ok(Draft),
+            machine().fire(PendingReview, Reject, &mut context)
+        );
+    }
+
+    #[test]
+    fn draws_every_transition() {
+        let dot = to_dot();
+
+        assert!(dot.starts_with("digraph \"post\" {"));
+        assert!(dot.contains("start -> \"draft\";"));
+        assert!(dot.contains(
+            "\"pending review\" -> \"scheduled\" [label=\"schedule [approved, publish time set, unblocked]\"];"
+        ));
+        assert!(dot.contains("\"archived\" -> \"published\" [label=\"unarchive\"];"));
+        assert!(!dot.contains("\"deleted\" ->"));
+    }
+}
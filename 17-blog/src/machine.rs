@@ -0,0 +1,358 @@
+//! A small, generic state-machine library.
+//!
+//! A [`Machine`] is a table of transitions between states of type `S`,
+//! triggered by events of type `E`. A transition can have a guard, a check on
+//! some context `C` that decides whether it applies, and states can have
+//! hooks that update the context when the machine enters or leaves them. The
+//! machine itself holds no current state; [`Machine::fire`] takes the state
+//! and context from the caller, so one machine can drive any number of
+//! objects.
+//!
+//! The post workflow is built on it (see [`lifecycle`](crate::lifecycle)),
+//! but nothing here knows about posts. An expense report, say:
+//!
+//! ```
+//! use blog::machine::Machine;
+//!
+//! #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+//! enum Report { Open, Submitted, Paid }
+//!
+//! #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+//! enum Event { Submit, Approve }
+//!
+//! struct Expense { amount: u32, approvals: u32, paid: bool }
+//!
+//! let machine = Machine::new(Report::Open)
+//!     .transition(Report::Open, Event::Submit, Report::Submitted)
+//!     // Large amounts need a second approval.
+//!     .guarded(Report::Submitted, Event::Approve, Report::Paid, "enough approvals", |e: &Expense| {
+//!         e.approvals >= if e.amount < 1000 { 1 } else { 2 }
+//!     })
+//!     .transition(Report::Submitted, Event::Approve, Report::Submitted)
+//!     .on_enter(Report::Paid, |e| e.paid = true);
+//!
+//! let mut expense = Expense { amount: 5000, approvals: 0, paid: false };
+//! let state = machine.fire(Report::Open, Event::Submit, &mut expense).unwrap();
+//! assert!(machine.fire(state, Event::Submit, &mut expense).is_err());
+//!
+//! // Guards only look at the context, so an approval is recorded in it before
+//! // the event is fired. The first one isn't enough...
+//! expense.approvals += 1;
+//! let state = machine.fire(state, Event::Approve, &mut expense).unwrap();
+//! assert_eq!(Report::Submitted, state);
+//!
+//! // ...but the second one is.
+//! expense.approvals += 1;
+//! let state = machine.fire(state, Event::Approve, &mut expense).unwrap();
+//! assert_eq!(Report::Paid, state);
+//! assert!(expense.paid);
+//! ```
+//!
+//! [`Machine::to_dot`] draws the machine as a Graphviz diagram.
+
+use std::fmt::{self, Write};
+
+use crate::error::NoTransition;
+
+type Check<C> = Box<dyn Fn(&C) -> bool + Send + Sync>;
+type Hook<C> = Box<dyn Fn(&mut C) + Send + Sync>;
+
+struct Guard<C> {
+    /// Describes the guard in diagrams.
+    name: String,
+    check: Check<C>,
+}
+
+struct Transition<S, E, C> {
+    from: S,
+    event: E,
+    to: S,
+    guard: Option<Guard<C>>,
+}
+
+pub struct Machine<S, E, C> {
+    initial: S,
+    transitions: Vec<Transition<S, E, C>>,
+    on_enter: Vec<(S, Hook<C>)>,
+    on_exit: Vec<(S, Hook<C>)>,
+}
+
+impl<S: Copy + Eq, E: Copy + Eq, C> Machine<S, E, C> {
+    /// Creates a machine without transitions. `initial` is where objects
+    /// driven by the machine start out.
+    pub fn new(initial: S) -> Machine<S, E, C> {
+        Machine {
+            initial,
+            transitions: Vec::new(),
+            on_enter: Vec::new(),
+            on_exit: Vec::new(),
+        }
+    }
+
+    pub fn initial(&self) -> S {
+        self.initial
+    }
+
+    /// Adds a transition: in state `from`, `event` leads to `to`.
+    ///
+    /// There may be several transitions for the same state and event. They
+    /// are tried in the order they were added, and the first one whose guard
+    /// passes is taken, so an unguarded one after guarded ones acts as the
+    /// fallback.
+    pub fn transition(mut self, from: S, event: E, to: S) -> Machine<S, E, C> {
+        self.transitions.push(Transition {
+            from,
+            event,
+            to,
+            guard: None,
+        });
+        self
+    }
+
+    /// Adds a transition that is only taken if `guard` returns `true` for the
+    /// context. `name` describes the guard in diagrams.
+    pub fn guarded(
+        mut self,
+        from: S,
+        event: E,
+        to: S,
+        name: &str,
+        guard: impl Fn(&C) -> bool + Send + Sync + 'static,
+    ) -> Machine<S, E, C> {
+        self.transitions.push(Transition {
+            from,
+            event,
+            to,
+            guard: Some(Guard {
+                name: name.to_string(),
+                check: Box::new(guard),
+            }),
+        });
+        self
+    }
+
+    /// Runs `hook` whenever the machine enters `state` from another state.
+    pub fn on_enter(
+        mut self,
+        state: S,
+        hook: impl Fn(&mut C) + Send + Sync + 'static,
+    ) -> Machine<S, E, C> {
+        self.on_enter.push((state, Box::new(hook)));
+        self
+    }
+
+    /// Runs `hook` whenever the machine leaves `state` for another state.
+    pub fn on_exit(
+        mut self,
+        state: S,
+        hook: impl Fn(&mut C) + Send + Sync + 'static,
+    ) -> Machine<S, E, C> {
+        self.on_exit.push((state, Box::new(hook)));
+        self
+    }
+
+    /// Whether there is any transition for `event` in `state`, regardless of
+    /// guards.
+    pub fn handles(&self, state: S, event: E) -> bool {
+        self.transitions
+            .iter()
+            .any(|t| t.from == state && t.event == event)
+    }
+
+    /// Every state `event` may lead to from `state`, depending on guards.
+    pub fn targets(&self, state: S, event: E) -> Vec<S> {
+        let mut targets = Vec::new();
+
+        for t in &self.transitions {
+            if t.from == state && t.event == event && !targets.contains(&t.to) {
+                targets.push(t.to);
+            }
+        }
+        targets
+    }
+
+    /// Every state the machine mentions, starting with the initial one.
+    pub fn states(&self) -> Vec<S> {
+        let mut states = vec![self.initial];
+
+        for t in &self.transitions {
+            for state in [t.from, t.to] {
+                if !states.contains(&state) {
+                    states.push(state);
+                }
+            }
+        }
+        states
+    }
+
+    /// Handles `event` in `state`: takes the first transition whose guard
+    /// passes and returns the state it leads to.
+    ///
+    /// When that is a different state, the exit hooks of `state` run first
+    /// and then the enter hooks of the new state. A transition back to the
+    /// same state runs no hooks. If no transition applies, the context is
+    /// left alone.
+    pub fn fire(&self, state: S, event: E, context: &mut C) -> Result<S, NoTransition<S, E>> {
+        let transition = self
+            .transitions
+            .iter()
+            .filter(|t| t.from == state && t.event == event)
+            .find(|t| t.guard.as_ref().is_none_or(|g| (g.check)(context)))
+            .ok_or(NoTransition { state, event })?;
+
+        let to = transition.to;
+        if to != state {
+            for (_, hook) in self.on_exit.iter().filter(|(s, _)| *s == state) {
+                hook(context);
+            }
+            for (_, hook) in self.on_enter.iter().filter(|(s, _)| *s == to) {
+                hook(context);
+            }
+        }
+        Ok(to)
+    }
+}
+
+impl<S: Copy + Eq + fmt::Display, E: Copy + Eq + fmt::Display, C> Machine<S, E, C> {
+    /// Draws the machine as a Graphviz DOT graph called `name`. Edges are
+    /// labelled with their event, and their guard in brackets.
+    pub fn to_dot(&self, name: &str) -> String {
+        let mut dot = String::new();
+
+        // Writing to a String can't fail.
+        writeln!(dot, "digraph {} {{", quote(name)).unwrap();
+        writeln!(dot, "    rankdir=LR;").unwrap();
+        writeln!(dot, "    start [shape=point];").unwrap();
+        for state in self.states() {
+            writeln!(dot, "    {};", quote(&state.to_string())).unwrap();
+        }
+        writeln!(dot, "    start -> {};", quote(&self.initial.to_string())).unwrap();
+
+        for t in &self.transitions {
+            let label = match &t.guard {
+                Some(guard) => format!("{} [{}]", t.event, guard.name),
+                None => t.event.to_string(),
+            };
+            writeln!(
+                dot,
+                "    {} -> {} [label={}];",
+                quote(&t.from.to_string()),
+                quote(&t.to.to_string()),
+                quote(&label)
+            )
+            .unwrap();
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// Quotes `id` as a DOT string.
+fn quote(id: &str) -> String {
+    format!("\"{}\"", id.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Deploy {
+        Requested,
+        Approved,
+        Live,
+    }
+
+    impl fmt::Display for Deploy {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            fmt::Debug::fmt(self, f)
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct Log {
+        entries: Vec<&'static str>,
+        frozen: bool,
+    }
+
+    fn machine() -> Machine<Deploy, &'static str, Log> {
+        Machine::new(Deploy::Requested)
+            .transition(Deploy::Requested, "approve", Deploy::Approved)
+            .guarded(
+                Deploy::Approved,
+                "ship",
+                Deploy::Live,
+                "not frozen",
+                |log: &Log| !log.frozen,
+            )
+            .transition(Deploy::Approved, "ship", Deploy::Approved)
+            .on_exit(Deploy::Requested, |log| log.entries.push("exit requested"))
+            .on_enter(Deploy::Approved, |log| log.entries.push("enter approved"))
+            .on_exit(Deploy::Approved, |log| log.entries.push("exit approved"))
+    }
+
+    #[test]
+    fn fires_the_first_transition_whose_guard_passes() {
+        let machine = machine();
+        let mut log = Log {
+            frozen: true,
+            ..Log::default()
+        };
+
+        let state = machine
+            .fire(Deploy::Requested, "approve", &mut log)
+            .unwrap();
+        assert_eq!(Deploy::Approved, state);
+        assert_eq!(vec!["exit requested", "enter approved"], log.entries);
+
+        // Frozen: the fallback keeps the deploy where it is, without hooks.
+        assert_eq!(Ok(Deploy::Approved), machine.fire(state, "ship", &mut log));
+        assert_eq!(2, log.entries.len());
+
+        log.frozen = false;
+        assert_eq!(Ok(Deploy::Live), machine.fire(state, "ship", &mut log));
+        assert_eq!("exit approved", log.entries[2]);
+
+        assert_eq!(
+            Err(NoTransition {
+                state: Deploy::Live,
+                event: "ship"
+            }),
+            machine.fire(Deploy::Live, "ship", &mut log)
+        );
+        assert_eq!(3, log.entries.len());
+    }
+
+    #[test]
+    fn describes_itself() {
+        let machine = machine();
+
+        assert!(machine.handles(Deploy::Approved, "ship"));
+        assert!(!machine.handles(Deploy::Live, "ship"));
+        assert_eq!(
+            vec![Deploy::Live, Deploy::Approved],
+            machine.targets(Deploy::Approved, "ship")
+        );
+        assert_eq!(
+            vec![Deploy::Requested, Deploy::Approved, Deploy::Live],
+            machine.states()
+        );
+
+        assert_eq!(
+            "digraph \"deploy\" {
+    rankdir=LR;
+    start [shape=point];
+    \"Requested\";
+    \"Approved\";
+    \"Live\";
+    start -> \"Requested\";
+    \"Requested\" -> \"Approved\" [label=\"approve\"];
+    \"Approved\" -> \"Live\" [label=\"ship [not frozen]\"];
+    \"Approved\" -> \"Approved\" [label=\"ship\"];
+}
+",
+            machine.to_dot("deploy")
+        );
+    }
+}
@@ -1,14 +1,131 @@
-use blog::Post;
+use std::{env, path::Path, process};
+
+use blog::{
+    lifecycle,
+    policy::Actor,
+    repository::FileRepository,
+    site::{Site, Templates},
+    Post,
+};
+
+const USAGE: &str = "\
+Usage: blog [build <posts dir> <output dir> [options] | lifecycle]
+
+Without a command, walks a post through the review workflow. `lifecycle`
+prints the workflow as a Graphviz diagram.
+
+Options for build:
+  --title <title>     Title of the site [default: Blog]
+  --base-url <url>    Where the site will be hosted [default: http://localhost/]
+  --templates <dir>   Directory with layout.html, post.html and item.html";
 
 fn main() {
-    let mut post = Post::new();
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match args.first().map(String::as_str) {
+        None => demo(),
+        Some("build") => build(&args[1..]).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            process::exit(1);
+        }),
+        Some("lifecycle") if args.len() == 1 => print!("{}", lifecycle::to_dot()),
+        Some(_) => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    }
+}
+
+/// Builds a static site from the posts stored in a directory.
+fn build(args: &[String]) -> Result<(), String> {
+    let mut paths = Vec::new();
+    let mut title = String::from("Blog");
+    let mut base_url = String::from("http://localhost/");
+    let mut templates = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("{} needs a value\n\n{}", arg, USAGE))
+        };
+
+        match arg.as_str() {
+            "--title" => title = value()?,
+            "--base-url" => base_url = value()?,
+            "--templates" => templates = Some(value()?),
+            option if option.starts_with("--") => {
+                return Err(format!("unknown option {}\n\n{}", option, USAGE))
+            }
+            path => paths.push(path),
+        }
+    }
+
+    let [posts, out] = paths[..] else {
+        return Err(USAGE.to_string());
+    };
+
+    let repository = FileRepository::open(posts).map_err(|e| e.to_string())?;
+    let mut site = Site::new(&title, &base_url);
+    if let Some(dir) = templates {
+        site = site.with_templates(Templates::load(Path::new(&dir)).map_err(|e| e.to_string())?);
+    }
+
+    let count = site
+        .build(&repository, Path::new(out))
+        .map_err(|e| e.to_string())?;
+    println!("Built {} posts into {}", count, out);
+    Ok(())
+}
+
+fn demo() {
+    let author = Actor::author("author");
+    let alice = Actor::editor("alice");
+    let bob = Actor::editor("bob");
+
+    let mut post = Post::with_required_approvals(2);
+    post.add_author(&author.name);
+
+    post.add_text(&author, "I ate a salad for lunch today")
+        .unwrap();
+    assert_eq!("", post.content());
+
+    // Only the post's authors and editors may edit it.
+    println!(
+        "{}",
+        post.add_text(&Actor::author("mallory"), "!").unwrap_err()
+    );
 
-    post.add_text("I ate a salad for lunch today");
+    // A draft can't be approved; the error says why nothing happened.
+    let error = post.approve(&alice).unwrap_err();
+    println!("{}", error);
+
+    post.request_review(&author).unwrap();
+    assert_eq!("", post.content());
+
+    // Posts under review can't be edited, and authors can't approve.
+    assert!(post.add_text(&author, " and a sandwich").is_err());
+    println!("{}", post.approve(&author).unwrap_err());
+
+    post.approve(&alice).unwrap();
+    post.reject(&bob).unwrap();
     assert_eq!("", post.content());
 
-    post.request_review();
+    post.add_text(&author, " and a sandwich").unwrap();
+    for line in post.diff(1, 2).unwrap() {
+        println!("{}", line);
+    }
+    post.restore(&author, 1).unwrap();
+
+    post.request_review(&author).unwrap();
+    post.approve(&alice).unwrap();
     assert_eq!("", post.content());
 
-    post.approve();
+    post.approve(&bob).unwrap();
     assert_eq!("I ate a salad for lunch today", post.content());
+
+    for entry in post.audit_log() {
+        println!("{} -> {} by {}", entry.from, entry.to, entry.actor);
+    }
 }
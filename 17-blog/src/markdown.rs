@@ -0,0 +1,140 @@
+//! Rendering of Markdown content to HTML that is safe to embed in a page.
+
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+
+/// Renders `markdown` (CommonMark with tables and strikethrough) to HTML.
+///
+/// The output is sanitized: raw HTML in the source is escaped and shown as
+/// text rather than passed through, and links or images pointing anywhere
+/// but `http`, `https`, `mailto` or a relative URL lose their target, so
+/// content can't smuggle scripts into the page.
+pub fn render_html(markdown: &str) -> String {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH;
+
+    let events = Parser::new_ext(markdown, options).map(|event| match event {
+        Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+        Event::Start(Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => Event::Start(Tag::Link {
+            link_type,
+            dest_url: safe_url(dest_url),
+            title,
+            id,
+        }),
+        Event::Start(Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => Event::Start(Tag::Image {
+            link_type,
+            dest_url: safe_url(dest_url),
+            title,
+            id,
+        }),
+        event => event,
+    });
+
+    let mut output = String::new();
+    html::push_html(&mut output, events);
+    output
+}
+
+fn safe_url(url: CowStr) -> CowStr {
+    if is_safe_url(&url) {
+        url
+    } else {
+        CowStr::Borrowed("")
+    }
+}
+
+fn is_safe_url(url: &str) -> bool {
+    // Browsers ignore whitespace and control characters inside the scheme,
+    // so `java\tscript:` is still a script URL.
+    let url: String = url
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+
+    // A colon before any `/`, `?` or `#` means the URL has a scheme; without
+    // one it is relative to the page.
+    match url.find([':', '/', '?', '#']) {
+        Some(i) if url[i..].starts_with(':') => {
+            matches!(&url[..i], "http" | "https" | "mailto")
+        }
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_common_markdown() {
+        let markdown = "\
+# Lunch
+
+I ate a *salad* and **soup**.
+
+- lettuce
+- tomato
+
+```rust
+let x = 1 < 2;
+```
+
+[recipe](https://example.com/salad)";
+
+        assert_eq!(
+            "\
+<h1>Lunch</h1>
+<p>I ate a <em>salad</em> and <strong>soup</strong>.</p>
+<ul>
+<li>lettuce</li>
+<li>tomato</li>
+</ul>
+<pre><code class=\"language-rust\">let x = 1 &lt; 2;
+</code></pre>
+<p><a href=\"https://example.com/salad\">recipe</a></p>
+",
+            render_html(markdown)
+        );
+    }
+
+    #[test]
+    fn escapes_raw_html() {
+        assert_eq!(
+            "&lt;script&gt;alert(1)&lt;/script&gt;",
+            render_html("<script>alert(1)</script>")
+        );
+        assert_eq!(
+            "<p>hi &lt;img src=x onerror=alert(1)&gt;</p>\n",
+            render_html("hi <img src=x onerror=alert(1)>")
+        );
+    }
+
+    #[test]
+    fn drops_unsafe_urls() {
+        assert_eq!(
+            "<p><a href=\"\">click</a></p>\n",
+            render_html("[click](javascript:alert(1))")
+        );
+        assert_eq!(
+            "<p><a href=\"\">click</a></p>\n",
+            render_html("[click](<JAVA\tSCRIPT:alert(1)>)")
+        );
+        assert_eq!(
+            "<p><img src=\"\" alt=\"x\" /></p>\n",
+            render_html("![x](data:text/html;base64,PHNjcmlwdD4=)")
+        );
+        assert_eq!(
+            "<p><a href=\"/posts/salad?x=a:b\">salad</a> <a href=\"mailto:me@example.com\">me</a></p>\n",
+            render_html("[salad](/posts/salad?x=a:b) [me](mailto:me@example.com)")
+        );
+    }
+}
@@ -0,0 +1,104 @@
+//! Everything about a post besides its content and state, and the URL slugs
+//! made from post titles.
+
+use std::{collections::BTreeSet, time::SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Metadata {
+    pub title: String,
+    /// In the order they were added, without duplicates.
+    pub authors: Vec<String>,
+    /// Stored in lowercase, so that `Rust` and `rust` are the same tag.
+    pub tags: BTreeSet<String>,
+    pub created_at: SystemTime,
+    /// When the content or metadata last changed. State transitions don't
+    /// count; they are in the audit log.
+    pub updated_at: SystemTime,
+}
+
+impl Metadata {
+    pub fn new(now: SystemTime) -> Metadata {
+        Metadata {
+            title: String::new(),
+            authors: Vec::new(),
+            tags: BTreeSet::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+// Posts saved before they had metadata are loaded as untitled and dated at
+// the epoch rather than failing to load.
+impl Default for Metadata {
+    fn default() -> Self {
+        Metadata::new(SystemTime::UNIX_EPOCH)
+    }
+}
+
+/// Turns a title into something that can be used in a URL: non-ASCII
+/// characters are transliterated (`ü` becomes `u`, `東京` becomes `dong-jing`),
+/// everything is lowercased, and each run of other characters becomes a
+/// single `-`. A title with nothing usable in it gives `post`.
+pub fn slugify(title: &str) -> String {
+    let ascii = deunicode::deunicode(title);
+    let mut slug = String::with_capacity(ascii.len());
+
+    for word in ascii
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+    {
+        if !slug.is_empty() {
+            slug.push('-');
+        }
+        slug.push_str(&word.to_ascii_lowercase());
+    }
+
+    if slug.is_empty() {
+        slug.push_str("post");
+    }
+    slug
+}
+
+/// Like [`slugify`], but appends `-2`, `-3` and so on until the slug isn't
+/// `taken`.
+pub fn unique_slug(title: &str, taken: impl Fn(&str) -> bool) -> String {
+    let slug = slugify(title);
+
+    if !taken(&slug) {
+        return slug;
+    }
+
+    (2..)
+        .map(|n| format!("{}-{}", slug, n))
+        .find(|candidate| !taken(candidate))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slugs_are_lowercase_ascii() {
+        assert_eq!("hello-world", slugify("Hello, World!"));
+        assert_eq!("uber-strasse", slugify("  Über   Straße "));
+        assert_eq!(
+            "creme-brulee-in-10-minutes",
+            slugify("Crème brûlée in 10 minutes")
+        );
+        assert_eq!("dong-jing", slugify("東京"));
+        assert_eq!("post", slugify("?!"));
+    }
+
+    #[test]
+    fn unique_slugs_get_a_number() {
+        let taken = ["hello", "hello-2"];
+        let taken = |slug: &str| taken.contains(&slug);
+
+        assert_eq!("hello-3", unique_slug("Hello", taken));
+        assert_eq!("goodbye", unique_slug("Goodbye", taken));
+    }
+}
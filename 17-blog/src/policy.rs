@@ -0,0 +1,159 @@
+//! Who may do what to a post.
+//!
+//! Every transition and every edit is performed by an [`Actor`], and before
+//! the post's state is even asked, the post's [`Policy`] decides whether that
+//! actor is allowed to do it at all.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{review::Thread, Action, Post};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Writes posts.
+    Author,
+    /// Reviews other people's posts and manages published ones.
+    Editor,
+    /// Can do anything an editor can, and delete posts.
+    Admin,
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Role::Author => "author",
+            Role::Editor => "editor",
+            Role::Admin => "admin",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Someone performing an action on a post.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Actor {
+    pub name: String,
+    pub role: Role,
+}
+
+impl Actor {
+    pub fn new(name: &str, role: Role) -> Actor {
+        Actor {
+            name: name.to_string(),
+            role,
+        }
+    }
+
+    pub fn author(name: &str) -> Actor {
+        Actor::new(name, Role::Author)
+    }
+
+    pub fn editor(name: &str) -> Actor {
+        Actor::new(name, Role::Editor)
+    }
+
+    pub fn admin(name: &str) -> Actor {
+        Actor::new(name, Role::Admin)
+    }
+
+    /// Whether the actor is one of the authors of `post`.
+    pub fn wrote(&self, post: &Post) -> bool {
+        post.authors().contains(&self.name)
+    }
+}
+
+impl fmt::Display for Actor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.role, self.name)
+    }
+}
+
+/// Decides whether an actor may perform an action on a post.
+///
+/// The policy only judges who is asking; whether the action makes sense in
+/// the post's current state is still up to the state machine.
+pub trait Policy: Send + Sync {
+    fn permits(&self, actor: &Actor, action: Action, post: &Post) -> bool;
+
+    /// Whether the actor may change the title or content of the post.
+    fn may_edit(&self, actor: &Actor, post: &Post) -> bool;
+
+    /// Whether the actor may resolve, or unresolve, a review thread of the
+    /// post.
+    fn may_resolve(&self, actor: &Actor, thread: &Thread, post: &Post) -> bool;
+}
+
+/// The policy posts use unless given another one:
+///
+/// - authors may edit and request review of posts they wrote; editors and
+///   admins may edit and request review of any post;
+/// - editors and admins may approve, schedule and reject posts, but nobody
+///   may approve or schedule a post they wrote themselves;
+/// - editors and admins may archive, unarchive and unpublish posts;
+/// - only admins may delete posts;
+/// - review threads may be resolved and unresolved by whoever started them,
+///   and by editors and admins, so an author can't wave away a reviewer's
+///   blocking comment.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DefaultPolicy;
+
+impl Policy for DefaultPolicy {
+    fn permits(&self, actor: &Actor, action: Action, post: &Post) -> bool {
+        let editor = matches!(actor.role, Role::Editor | Role::Admin);
+
+        match action {
+            Action::RequestReview => editor || actor.wrote(post),
+            Action::Approve | Action::Schedule => editor && !actor.wrote(post),
+            Action::Reject | Action::Archive | Action::Unarchive | Action::Unpublish => editor,
+            Action::Delete => actor.role == Role::Admin,
+            // Only the scheduler publishes, and it doesn't go through the
+            // policy.
+            Action::Publish => false,
+        }
+    }
+
+    fn may_edit(&self, actor: &Actor, post: &Post) -> bool {
+        matches!(actor.role, Role::Editor | Role::Admin) || actor.wrote(post)
+    }
+
+    fn may_resolve(&self, actor: &Actor, thread: &Thread, _: &Post) -> bool {
+        matches!(actor.role, Role::Editor | Role::Admin) || thread.started_by() == actor.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_policy() {
+        let mut post = Post::new();
+        post.add_author("alice");
+        post.add_author("bob");
+
+        let policy = DefaultPolicy;
+        let allowed = |actor: &Actor, action| policy.permits(actor, action, &post);
+
+        assert!(allowed(&Actor::author("alice"), Action::RequestReview));
+        assert!(!allowed(&Actor::author("carol"), Action::RequestReview));
+        assert!(allowed(&Actor::editor("carol"), Action::RequestReview));
+
+        assert!(!allowed(&Actor::author("carol"), Action::Approve));
+        assert!(allowed(&Actor::editor("carol"), Action::Approve));
+        assert!(!allowed(&Actor::editor("bob"), Action::Approve));
+        assert!(!allowed(&Actor::admin("bob"), Action::Schedule));
+        assert!(allowed(&Actor::editor("bob"), Action::Reject));
+
+        assert!(allowed(&Actor::editor("carol"), Action::Archive));
+        assert!(!allowed(&Actor::author("alice"), Action::Unpublish));
+        assert!(!allowed(&Actor::editor("carol"), Action::Delete));
+        assert!(allowed(&Actor::admin("dave"), Action::Delete));
+
+        assert!(policy.may_edit(&Actor::author("bob"), &post));
+        assert!(!policy.may_edit(&Actor::author("carol"), &post));
+        assert!(policy.may_edit(&Actor::editor("carol"), &post));
+    }
+}
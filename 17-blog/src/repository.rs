@@ -0,0 +1,353 @@
+//! Storage for posts, so that a workflow survives restarts.
+//!
+//! A [`Post`] holds a clock and a policy, which can't be serialized.
+//! Repositories store a [`PostRecord`] instead: a plain snapshot of
+//! everything in the post, including which state it is in, that can be turned
+//! back into an equivalent `Post`.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    audit::AuditEntry, clock::SystemClock, error::RepositoryError, lifecycle::Context,
+    metadata::Metadata, policy::DefaultPolicy, review::Thread, revision::Revision, Post, Status,
+};
+
+/// A serializable snapshot of a [`Post`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PostRecord {
+    pub status: Status,
+    /// Reviewers who approved the post, while it is pending review.
+    #[serde(default)]
+    pub approvals: Vec<String>,
+    /// When the post is to be published, if it has been scheduled.
+    #[serde(default)]
+    pub publish_at: Option<SystemTime>,
+    pub content: String,
+    pub required_approvals: usize,
+    #[serde(default)]
+    pub audit_log: Vec<AuditEntry>,
+    #[serde(default)]
+    pub revisions: Vec<Revision>,
+    #[serde(default)]
+    pub threads: Vec<Thread>,
+    #[serde(default)]
+    pub metadata: Metadata,
+}
+
+impl From<&Post> for PostRecord {
+    fn from(post: &Post) -> PostRecord {
+        PostRecord {
+            // The status of the state itself rather than Post::status, so a
+            // scheduled post is still scheduled when it is loaded again.
+            status: post.state,
+            approvals: post.approvals().into_iter().map(String::from).collect(),
+            publish_at: post.publish_at(),
+            content: post.content.clone(),
+            required_approvals: post.lifecycle.required_approvals,
+            audit_log: post.audit_log.clone(),
+            revisions: post.revisions.clone(),
+            threads: post.threads.clone(),
+            metadata: post.metadata.clone(),
+        }
+    }
+}
+
+impl From<PostRecord> for Post {
+    fn from(record: PostRecord) -> Post {
+        let state = match (record.status, record.publish_at) {
+            // A scheduled post without a time has nothing to wait for.
+            (Status::Scheduled, None) => Status::Published,
+            (status, _) => status,
+        };
+        let lifecycle = Context {
+            approvals: record.approvals.into_iter().collect(),
+            publish_at: record.publish_at,
+            ..Context::new(record.required_approvals)
+        };
+
+        Post {
+            state,
+            lifecycle,
+            content: record.content,
+            audit_log: record.audit_log,
+            revisions: record.revisions,
+            threads: record.threads,
+            metadata: record.metadata,
+            clock: Arc::new(SystemClock),
+            policy: Arc::new(DefaultPolicy),
+        }
+    }
+}
+
+/// Somewhere posts can be saved and loaded again by id.
+pub trait PostRepository {
+    /// Stores `post` under `id`, replacing any post already stored there.
+    fn save(&mut self, id: &str, post: &Post) -> Result<(), RepositoryError>;
+
+    /// Loads the post stored under `id`, or `None` if there isn't one.
+    fn load(&self, id: &str) -> Result<Option<Post>, RepositoryError>;
+
+    /// Removes the post stored under `id`, returning whether there was one.
+    fn delete(&mut self, id: &str) -> Result<bool, RepositoryError>;
+
+    /// The ids of all stored posts, in sorted order.
+    fn ids(&self) -> Result<Vec<String>, RepositoryError>;
+}
+
+/// Ids end up in file names, so they are limited to ASCII letters, digits,
+/// `-` and `_`.
+pub(crate) fn check_id(id: &str) -> Result<(), RepositoryError> {
+    let valid = !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    if valid {
+        Ok(())
+    } else {
+        Err(RepositoryError::InvalidId(id.to_string()))
+    }
+}
+
+/// The ids of the `<id>.<extension>` files in `dir`, in sorted order. Files
+/// whose name isn't a valid id are left out.
+pub(crate) fn ids_in(dir: &Path, extension: &str) -> Result<Vec<String>, RepositoryError> {
+    let mut ids = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(extension) {
+            continue;
+        }
+
+        if let Some(id) = path.file_stem().and_then(|s| s.to_str()) {
+            if check_id(id).is_ok() {
+                ids.push(id.to_string());
+            }
+        }
+    }
+
+    ids.sort();
+    Ok(ids)
+}
+
+/// Keeps posts in memory only; useful for tests and short-lived processes.
+#[derive(Debug, Default)]
+pub struct InMemoryRepository {
+    records: BTreeMap<String, PostRecord>,
+}
+
+impl InMemoryRepository {
+    pub fn new() -> InMemoryRepository {
+        InMemoryRepository::default()
+    }
+}
+
+impl PostRepository for InMemoryRepository {
+    fn save(&mut self, id: &str, post: &Post) -> Result<(), RepositoryError> {
+        check_id(id)?;
+        self.records.insert(id.to_string(), PostRecord::from(post));
+        Ok(())
+    }
+
+    fn load(&self, id: &str) -> Result<Option<Post>, RepositoryError> {
+        check_id(id)?;
+        Ok(self.records.get(id).cloned().map(Post::from))
+    }
+
+    fn delete(&mut self, id: &str) -> Result<bool, RepositoryError> {
+        check_id(id)?;
+        Ok(self.records.remove(id).is_some())
+    }
+
+    fn ids(&self) -> Result<Vec<String>, RepositoryError> {
+        Ok(self.records.keys().cloned().collect())
+    }
+}
+
+/// Keeps every post in its own `<id>.json` file inside a directory.
+///
+/// Files are written to a temporary file first and then renamed over the old
+/// one, so a crash mid-write never leaves a half-written post behind.
+#[derive(Debug)]
+pub struct FileRepository {
+    dir: PathBuf,
+}
+
+impl FileRepository {
+    /// Opens the repository in `dir`, creating the directory if needed.
+    pub fn open(dir: impl Into<PathBuf>) -> Result<FileRepository, RepositoryError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(FileRepository { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+}
+
+impl PostRepository for FileRepository {
+    fn save(&mut self, id: &str, post: &Post) -> Result<(), RepositoryError> {
+        check_id(id)?;
+
+        let json = serde_json::to_vec_pretty(&PostRecord::from(post))?;
+        // The leading dot keeps half-written files out of `ids`.
+        let temp = self.dir.join(format!(".{}.json.tmp", id));
+
+        let mut file = fs::File::create(&temp)?;
+        file.write_all(&json)?;
+        file.sync_all()?;
+        fs::rename(&temp, self.path(id))?;
+
+        Ok(())
+    }
+
+    fn load(&self, id: &str) -> Result<Option<Post>, RepositoryError> {
+        check_id(id)?;
+
+        let json = match fs::read(self.path(id)) {
+            Ok(json) => json,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let record: PostRecord = serde_json::from_slice(&json)?;
+        Ok(Some(Post::from(record)))
+    }
+
+    fn delete(&mut self, id: &str) -> Result<bool, RepositoryError> {
+        check_id(id)?;
+
+        match fs::remove_file(self.path(id)) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn ids(&self) -> Result<Vec<String>, RepositoryError> {
+        ids_in(&self.dir, "json")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::Actor;
+
+    fn sample_post() -> Post {
+        let mut post = Post::with_required_approvals(2);
+        post.add_author("author");
+        post.set_title(&Actor::author("author"), "Lunch").unwrap();
+        post.add_tag("food");
+        post.add_text(&Actor::author("author"), "I ate a salad")
+            .unwrap();
+        post.add_text(&Actor::author("author"), " for lunch today")
+            .unwrap();
+        post.request_review(&Actor::author("author")).unwrap();
+        post.approve(&Actor::editor("alice")).unwrap();
+        post.comment("alice", 8..13, "Which salad?", false).unwrap();
+        post
+    }
+
+    fn round_trip(repository: &mut dyn PostRepository) {
+        repository.save("salad", &sample_post()).unwrap();
+
+        let mut post = repository.load("salad").unwrap().unwrap();
+        assert_eq!(Status::PendingReview, post.status());
+        assert_eq!(vec!["alice"], post.approvals());
+        assert_eq!(2, post.revisions().len());
+        assert_eq!(2, post.audit_log().len());
+        assert_eq!("Lunch", post.title());
+        assert_eq!(vec!["food"], post.tags());
+        assert_eq!("salad", post.thread(1).unwrap().quote);
+
+        // The restored post carries on where the saved one left off.
+        post.approve(&Actor::editor("alice")).unwrap();
+        assert_eq!("", post.content());
+        post.approve(&Actor::editor("bob")).unwrap();
+        assert_eq!("I ate a salad for lunch today", post.content());
+
+        repository.save("salad", &post).unwrap();
+        let post = repository.load("salad").unwrap().unwrap();
+        assert_eq!(Status::Published, post.status());
+
+        repository.save("other", &Post::new()).unwrap();
+        assert_eq!(vec!["other", "salad"], repository.ids().unwrap());
+
+        assert!(repository.delete("salad").unwrap());
+        assert!(!repository.delete("salad").unwrap());
+        assert!(repository.load("salad").unwrap().is_none());
+
+        let mut post = Post::new();
+
+        post.add_author("author");
+        post.add_text(&Actor::author("author"), "Embargoed news")
+            .unwrap();
+        post.request_review(&Actor::author("author")).unwrap();
+        let publish_at = SystemTime::now() + std::time::Duration::from_secs(3600);
+        post.schedule(&Actor::editor("alice"), publish_at).unwrap();
+        repository.save("news", &post).unwrap();
+
+        let post = repository.load("news").unwrap().unwrap();
+        assert_eq!(Status::Scheduled, post.status());
+        assert_eq!(Some(publish_at), post.publish_at());
+    }
+
+    #[test]
+    fn in_memory_round_trip() {
+        round_trip(&mut InMemoryRepository::new());
+    }
+
+    #[test]
+    fn file_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut repository = FileRepository::open(dir.path().join("posts")).unwrap();
+
+        round_trip(&mut repository);
+
+        // Reopening the directory sees the same posts.
+        let repository = FileRepository::open(dir.path().join("posts")).unwrap();
+        assert_eq!(vec!["news", "other"], repository.ids().unwrap());
+        assert!(!repository.dir().join(".other.json.tmp").exists());
+    }
+
+    #[test]
+    fn rejects_ids_that_are_not_file_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut repository = FileRepository::open(dir.path()).unwrap();
+
+        for id in ["", "../escape", "a/b", ".hidden"] {
+            assert!(matches!(
+                repository.save(id, &Post::new()),
+                Err(RepositoryError::InvalidId(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn reports_corrupt_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let repository = FileRepository::open(dir.path()).unwrap();
+        fs::write(dir.path().join("broken.json"), "{ not json").unwrap();
+
+        assert!(matches!(
+            repository.load("broken"),
+            Err(RepositoryError::Format(_))
+        ));
+    }
+}
@@ -0,0 +1,49 @@
+//! Review comments on a post, grouped into threads.
+//!
+//! A thread starts with a comment on a stretch of the content and collects
+//! the replies to it. Blocking threads hold up the review: a post can't be
+//! approved while any of them is unresolved.
+
+use std::{ops::Range, time::SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Comment {
+    pub author: String,
+    pub text: String,
+    pub at: SystemTime,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Thread {
+    /// Threads are numbered from 1 in the order they were started.
+    pub id: usize,
+    /// The byte range of the content the thread is about.
+    pub range: Range<usize>,
+    /// The revision `range` refers to; the content may have changed since.
+    pub revision: usize,
+    /// The text in `range` when the thread was started.
+    pub quote: String,
+    pub blocking: bool,
+    /// Who resolved the thread, if it is resolved.
+    pub resolved_by: Option<String>,
+    /// The comment that started the thread, then the replies, oldest first.
+    pub comments: Vec<Comment>,
+}
+
+impl Thread {
+    /// Who wrote the comment that started the thread.
+    pub fn started_by(&self) -> &str {
+        &self.comments[0].author
+    }
+
+    pub fn is_resolved(&self) -> bool {
+        self.resolved_by.is_some()
+    }
+
+    /// Whether the thread keeps the post from being approved.
+    pub fn is_blocking(&self) -> bool {
+        self.blocking && !self.is_resolved()
+    }
+}
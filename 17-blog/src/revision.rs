@@ -0,0 +1,106 @@
+//! Snapshots of a post's content, taken every time it is edited.
+
+use std::{fmt, time::SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Revision {
+    /// Revisions are numbered from 1 in the order they were made.
+    pub number: usize,
+    /// The full content of the post after the edit.
+    pub content: String,
+    pub at: SystemTime,
+}
+
+/// One line of a [`diff`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffLine<'a> {
+    Unchanged(&'a str),
+    Added(&'a str),
+    Removed(&'a str),
+}
+
+impl fmt::Display for DiffLine<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiffLine::Unchanged(line) => write!(f, "  {}", line),
+            DiffLine::Added(line) => write!(f, "+ {}", line),
+            DiffLine::Removed(line) => write!(f, "- {}", line),
+        }
+    }
+}
+
+/// Compares two texts line by line, keeping as many lines unchanged as
+/// possible (the longest common subsequence). Removed lines come before the
+/// lines added in their place.
+pub fn diff<'a>(old: &'a str, new: &'a str) -> Vec<DiffLine<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // common[i][j] is the length of the longest common subsequence of
+    // old[i..] and new[j..].
+    let mut common = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            lines.push(DiffLine::Unchanged(old[i]));
+            i += 1;
+            j += 1;
+        } else if common[i + 1][j] >= common[i][j + 1] {
+            lines.push(DiffLine::Removed(old[i]));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(new[j]));
+            j += 1;
+        }
+    }
+
+    lines.extend(old[i..].iter().map(|line| DiffLine::Removed(line)));
+    lines.extend(new[j..].iter().map(|line| DiffLine::Added(line)));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_keeps_common_lines() {
+        let old = "title\nfirst\nsecond\nend";
+        let new = "title\nsecond\nthird\nend";
+
+        assert_eq!(
+            vec![
+                DiffLine::Unchanged("title"),
+                DiffLine::Removed("first"),
+                DiffLine::Unchanged("second"),
+                DiffLine::Added("third"),
+                DiffLine::Unchanged("end"),
+            ],
+            diff(old, new)
+        );
+    }
+
+    #[test]
+    fn diff_against_empty_text() {
+        assert_eq!(
+            vec![DiffLine::Added("a"), DiffLine::Added("b")],
+            diff("", "a\nb")
+        );
+        assert_eq!(vec![DiffLine::Removed("a")], diff("a", ""));
+        assert_eq!("+ a", DiffLine::Added("a").to_string());
+    }
+}
@@ -0,0 +1,346 @@
+//! Full-text search over the titles and content of posts.
+//!
+//! Posts are split into words that are lowercased and stripped of accents,
+//! but not stemmed, so `Café` finds `cafe` but `salads` doesn't find
+//! `salad`. Results are ranked with BM25, with words in the title counting
+//! more than words in the content.
+//!
+//! Searches are made on behalf of an [`Audience`]. Readers only find posts
+//! whose content [`Post::content`] would show them, and the ranking is
+//! computed as if the other posts weren't there at all, so that scores give
+//! nothing away about them either.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    sync::Arc,
+    time::SystemTime,
+};
+
+use crate::{
+    clock::{Clock, SystemClock},
+    error::RepositoryError,
+    repository::PostRepository,
+    Post, Status,
+};
+
+/// How much a word in the title counts, compared to one in the content.
+const TITLE_WEIGHT: usize = 3;
+
+// The usual BM25 parameters: how quickly repeating a word stops helping, and
+// how much longer posts are penalized.
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+
+/// Who a search is made for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Audience {
+    /// Finds only what is published.
+    Reader,
+    /// Finds every post that isn't deleted, e.g. drafts and posts in review.
+    Editor,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hit {
+    pub id: String,
+    pub title: String,
+    pub score: f64,
+}
+
+struct Document {
+    title: String,
+    /// The weighted number of words in the title and content.
+    length: usize,
+    /// The distinct words, to find the postings to remove.
+    terms: BTreeSet<String>,
+    /// From when readers may find the post; `None` if they may not.
+    public_from: Option<SystemTime>,
+}
+
+/// An inverted index of posts, by id.
+///
+/// The index holds a copy of what it needs from each post, so it has to be
+/// told about changes with [`SearchIndex::insert`]. The one exception is a
+/// scheduled post, which shows up for readers on its own once its publish
+/// time has come.
+pub struct SearchIndex {
+    documents: HashMap<String, Document>,
+    /// For every word, the posts containing it and how often (weighted).
+    postings: HashMap<String, HashMap<String, usize>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for SearchIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SearchIndex {
+    pub fn new() -> SearchIndex {
+        SearchIndex {
+            documents: HashMap::new(),
+            postings: HashMap::new(),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Makes reader searches take the current time from `clock`.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> SearchIndex {
+        self.clock = clock;
+        self
+    }
+
+    /// Indexes every post in `repository`.
+    pub fn from_repository(
+        repository: &dyn PostRepository,
+    ) -> Result<SearchIndex, RepositoryError> {
+        let mut index = SearchIndex::new();
+
+        for id in repository.ids()? {
+            if let Some(post) = repository.load(&id)? {
+                index.insert(&id, &post);
+            }
+        }
+        Ok(index)
+    }
+
+    /// Adds `post` under `id`, replacing whatever was indexed under `id`
+    /// before. Deleted posts are taken out of the index.
+    pub fn insert(&mut self, id: &str, post: &Post) {
+        self.remove(id);
+
+        let public_from = match post.status() {
+            // Only scheduled posts, due or not, have a publish time.
+            Status::Published | Status::Scheduled => {
+                Some(post.publish_at().unwrap_or(SystemTime::UNIX_EPOCH))
+            }
+            Status::Deleted => return,
+            _ => None,
+        };
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for word in tokenize(post.title()) {
+            *counts.entry(word).or_default() += TITLE_WEIGHT;
+        }
+        // The raw content rather than Post::content, which may be empty;
+        // whether readers may see it is up to public_from.
+        for word in tokenize(&post.content) {
+            *counts.entry(word).or_default() += 1;
+        }
+
+        for (word, count) in &counts {
+            self.postings
+                .entry(word.clone())
+                .or_default()
+                .insert(id.to_string(), *count);
+        }
+
+        self.documents.insert(
+            id.to_string(),
+            Document {
+                title: post.title().to_string(),
+                length: counts.values().sum(),
+                terms: counts.into_keys().collect(),
+                public_from,
+            },
+        );
+    }
+
+    /// Takes the post `id` out of the index, returning whether it was there.
+    pub fn remove(&mut self, id: &str) -> bool {
+        let Some(document) = self.documents.remove(id) else {
+            return false;
+        };
+
+        for term in &document.terms {
+            if let Some(posting) = self.postings.get_mut(term) {
+                posting.remove(id);
+                if posting.is_empty() {
+                    self.postings.remove(term);
+                }
+            }
+        }
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    /// The posts `audience` may find that contain any word of `query`, best
+    /// match first.
+    pub fn search(&self, query: &str, audience: Audience) -> Vec<Hit> {
+        let now = self.clock.now();
+        let findable = |document: &Document| match audience {
+            Audience::Editor => true,
+            Audience::Reader => document.public_from.is_some_and(|from| from <= now),
+        };
+
+        // Statistics only over what the audience may find.
+        let (count, total_length) = self
+            .documents
+            .values()
+            .filter(|document| findable(document))
+            .fold((0, 0), |(count, total), document| {
+                (count + 1, total + document.length)
+            });
+        if count == 0 {
+            return Vec::new();
+        }
+        let average_length = total_length.max(1) as f64 / count as f64;
+
+        let terms: BTreeSet<String> = tokenize(query).collect();
+        let mut scores: BTreeMap<&str, f64> = BTreeMap::new();
+
+        for term in &terms {
+            let Some(posting) = self.postings.get(term) else {
+                continue;
+            };
+
+            let matches: Vec<(&Document, &str, usize)> = posting
+                .iter()
+                .map(|(id, &frequency)| (&self.documents[id], id.as_str(), frequency))
+                .filter(|(document, _, _)| findable(document))
+                .collect();
+
+            let n = matches.len() as f64;
+            let idf = (1.0 + (count as f64 - n + 0.5) / (n + 0.5)).ln();
+
+            for (document, id, frequency) in matches {
+                let frequency = frequency as f64;
+                let length = document.length as f64 / average_length;
+                *scores.entry(id).or_default() +=
+                    idf * frequency * (K1 + 1.0) / (frequency + K1 * (1.0 - B + B * length));
+            }
+        }
+
+        let mut hits: Vec<Hit> = scores
+            .into_iter()
+            .map(|(id, score)| Hit {
+                id: id.to_string(),
+                title: self.documents[id].title.clone(),
+                score,
+            })
+            .collect();
+
+        // Ties keep the id order of the BTreeMap, as the sort is stable.
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        hits
+    }
+}
+
+/// Splits `text` into lowercase words without accents.
+fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| deunicode::deunicode(word).to_lowercase())
+        .flat_map(|word| {
+            // Transliteration can turn one word into several, e.g. for
+            // Chinese characters.
+            word.split(|c: char| !c.is_ascii_alphanumeric())
+                .filter(|w| !w.is_empty())
+                .map(String::from)
+                .collect::<Vec<_>>()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{clock::ManualClock, policy::Actor};
+    use std::time::Duration;
+
+    fn post(title: &str, text: &str) -> Post {
+        let mut post = Post::new();
+        post.add_author("author");
+        post.set_title(&Actor::author("author"), title).unwrap();
+        post.add_text(&Actor::author("author"), text).unwrap();
+        post
+    }
+
+    fn published(title: &str, text: &str) -> Post {
+        let mut post = post(title, text);
+        post.request_review(&Actor::author("author")).unwrap();
+        post.approve(&Actor::editor("editor")).unwrap();
+        post
+    }
+
+    fn ids(hits: &[Hit]) -> Vec<&str> {
+        hits.iter().map(|hit| hit.id.as_str()).collect()
+    }
+
+    #[test]
+    fn normalizes_words() {
+        let words: Vec<String> = tokenize("Crème brûlée, 2 WAYS! 東京").collect();
+        assert_eq!(vec!["creme", "brulee", "2", "ways", "dong", "jing"], words);
+    }
+
+    #[test]
+    fn ranks_matches() {
+        let mut index = SearchIndex::new();
+        index.insert(
+            "lunch",
+            &published("Lunch", "I ate a salad for lunch today"),
+        );
+        index.insert(
+            "salad",
+            &published("Salad", "A salad recipe with salad leaves"),
+        );
+        index.insert("soup", &published("Soup", "Nothing green here"));
+
+        let hits = index.search("SALAD", Audience::Reader);
+        assert_eq!(vec!["salad", "lunch"], ids(&hits));
+        assert!(hits[0].score > hits[1].score);
+        assert_eq!("Salad", hits[0].title);
+
+        // Any word matches; a rare word counts more than a common one.
+        assert_eq!(
+            vec!["soup", "salad", "lunch"],
+            ids(&index.search("green salad", Audience::Reader))
+        );
+        assert!(index.search("salads", Audience::Reader).is_empty());
+    }
+
+    #[test]
+    fn readers_only_find_what_they_can_read() {
+        let clock = Arc::new(ManualClock::new(SystemTime::UNIX_EPOCH));
+        let mut index = SearchIndex::new().with_clock(clock.clone());
+
+        index.insert("draft", &post("Secret salad", "Not yet"));
+        index.insert("public", &published("Lunch", "A salad"));
+
+        let mut scheduled = post("Embargo", "Salad news");
+        scheduled.request_review(&Actor::author("author")).unwrap();
+        scheduled
+            .schedule(
+                &Actor::editor("editor"),
+                SystemTime::UNIX_EPOCH + Duration::from_secs(60),
+            )
+            .unwrap();
+        index.insert("scheduled", &scheduled);
+
+        assert_eq!(
+            vec!["public"],
+            ids(&index.search("salad", Audience::Reader))
+        );
+        assert!(index.search("secret", Audience::Reader).is_empty());
+        assert_eq!(3, index.search("salad", Audience::Editor).len());
+
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(2, index.search("salad", Audience::Reader).len());
+
+        let mut deleted = published("Gone", "salad");
+        deleted.delete(&Actor::admin("admin")).unwrap();
+        index.insert("public", &deleted);
+        assert_eq!(
+            vec!["draft", "scheduled"],
+            ids(&index.search("salad", Audience::Editor))
+        );
+        assert_eq!(2, index.len());
+    }
+}
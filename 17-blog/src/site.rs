@@ -0,0 +1,414 @@
+//! Generates a static site from the published posts of a repository.
+//!
+//! The site has an index page listing every post, newest first, a page per
+//! post under `posts/`, a page per tag under `tags/` and an Atom feed in
+//! `feed.xml`. Pages are made from three small [`Templates`], so the look of
+//! the site can be changed without touching the code.
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    collection::Collection, error::SiteError, metadata, repository::PostRepository, Post, Status,
+};
+
+/// The templates pages are made from. A template is plain text in which
+/// `{{name}}` is replaced by the value called `name`:
+///
+/// - `layout` wraps every page: `title`, `site_title`, `body` and `root`,
+///   the relative path back to the top of the site (`""` or `"../"`);
+/// - `post` is the body of a post page: `title`, `date`, `authors`, `tags`
+///   and `content`;
+/// - `item` is one entry in a list of posts: `url`, `title` and `date`.
+///
+/// Values are already escaped, or are HTML where that is what is wanted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Templates {
+    pub layout: String,
+    pub post: String,
+    pub item: String,
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Templates {
+            layout: include_str!("../templates/layout.html").to_string(),
+            post: include_str!("../templates/post.html").to_string(),
+            item: include_str!("../templates/item.html").to_string(),
+        }
+    }
+}
+
+impl Templates {
+    /// Reads `layout.html`, `post.html` and `item.html` from `dir`, using
+    /// the built-in template for any file that isn't there.
+    pub fn load(dir: &Path) -> io::Result<Templates> {
+        let read = |name: &str, default: String| match fs::read_to_string(dir.join(name)) {
+            Ok(template) => Ok(template),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(default),
+            Err(e) => Err(e),
+        };
+
+        let defaults = Templates::default();
+        Ok(Templates {
+            layout: read("layout.html", defaults.layout)?,
+            post: read("post.html", defaults.post)?,
+            item: read("item.html", defaults.item)?,
+        })
+    }
+}
+
+pub struct Site {
+    pub title: String,
+    /// Where the site will be hosted. The feed needs absolute links.
+    pub base_url: String,
+    pub templates: Templates,
+}
+
+impl Site {
+    pub fn new(title: &str, base_url: &str) -> Site {
+        let mut base_url = base_url.to_string();
+        if !base_url.ends_with('/') {
+            base_url.push('/');
+        }
+
+        Site {
+            title: title.to_string(),
+            base_url,
+            templates: Templates::default(),
+        }
+    }
+
+    pub fn with_templates(mut self, templates: Templates) -> Site {
+        self.templates = templates;
+        self
+    }
+
+    /// Writes the site for the published posts in `repository` into `out`,
+    /// returning how many posts it contains. Files already in `out` are
+    /// overwritten but never deleted.
+    pub fn build(&self, repository: &dyn PostRepository, out: &Path) -> Result<usize, SiteError> {
+        let mut posts = Vec::new();
+        for id in repository.ids()? {
+            if let Some(post) = repository.load(&id)? {
+                if post.status() == Status::Published {
+                    posts.push(post);
+                }
+            }
+        }
+
+        // Oldest first, so that of two posts with the same title the older
+        // one keeps the plain slug no matter when the site is built.
+        posts.sort_by_key(published_at);
+
+        let mut collection = Collection::new();
+        let mut slugs: Vec<String> = posts.into_iter().map(|p| collection.insert(p)).collect();
+        slugs.reverse();
+
+        let posts: Vec<(&str, &Post)> = slugs
+            .iter()
+            .map(|slug| (slug.as_str(), collection.get(slug).unwrap()))
+            .collect();
+
+        let mut tags: BTreeMap<&str, Vec<(&str, &Post)>> = BTreeMap::new();
+        for &(slug, post) in &posts {
+            for tag in post.tags() {
+                tags.entry(tag).or_default().push((slug, post));
+            }
+        }
+
+        // Tags may contain anything, so their pages get slugs too.
+        let mut tag_slugs: BTreeMap<&str, String> = BTreeMap::new();
+        for &tag in tags.keys() {
+            let slug = metadata::unique_slug(tag, |s| tag_slugs.values().any(|t| t == s));
+            tag_slugs.insert(tag, slug);
+        }
+
+        fs::create_dir_all(out.join("posts"))?;
+        fs::create_dir_all(out.join("tags"))?;
+
+        let list = self.list(&posts, "");
+        let body = format!("<h1>{}</h1>\n{}", escape(&self.title), list);
+        fs::write(out.join("index.html"), self.page(&self.title, &body, ""))?;
+
+        for &(slug, post) in &posts {
+            let tags: Vec<String> = post
+                .tags()
+                .iter()
+                .map(|tag| {
+                    format!(
+                        "<a href=\"../tags/{}.html\">{}</a>",
+                        tag_slugs[tag],
+                        escape(tag)
+                    )
+                })
+                .collect();
+
+            let body = render(
+                &self.templates.post,
+                &[
+                    ("title", &escape(title(post))),
+                    ("date", &date(published_at(post))),
+                    ("authors", &escape(&post.authors().join(", "))),
+                    ("tags", &tags.join(" ")),
+                    ("content", &post.render_html()),
+                ],
+            );
+
+            let page = self.page(title(post), &body, "../");
+            fs::write(out.join("posts").join(format!("{}.html", slug)), page)?;
+        }
+
+        for (tag, posts) in &tags {
+            let title = format!("Posts tagged \u{201c}{}\u{201d}", tag);
+            let body = format!("<h1>{}</h1>\n{}", escape(&title), self.list(posts, "../"));
+
+            let page = self.page(&title, &body, "../");
+            fs::write(
+                out.join("tags").join(format!("{}.html", tag_slugs[tag])),
+                page,
+            )?;
+        }
+
+        fs::write(out.join("feed.xml"), self.feed(&posts))?;
+
+        Ok(posts.len())
+    }
+
+    fn page(&self, title: &str, body: &str, root: &str) -> String {
+        render(
+            &self.templates.layout,
+            &[
+                ("title", &escape(title)),
+                ("site_title", &escape(&self.title)),
+                ("body", body),
+                ("root", root),
+            ],
+        )
+    }
+
+    fn list(&self, posts: &[(&str, &Post)], root: &str) -> String {
+        let items: String = posts
+            .iter()
+            .map(|&(slug, post)| {
+                render(
+                    &self.templates.item,
+                    &[
+                        ("url", &format!("{}posts/{}.html", root, slug)),
+                        ("title", &escape(title(post))),
+                        ("date", &date(published_at(post))),
+                    ],
+                )
+            })
+            .collect();
+
+        format!("<ul class=\"posts\">\n{}</ul>", items)
+    }
+
+    fn feed(&self, posts: &[(&str, &Post)]) -> String {
+        let updated = posts
+            .iter()
+            .map(|(_, post)| updated_at(post))
+            .max()
+            .unwrap_or(UNIX_EPOCH);
+
+        let mut feed = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        feed.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+        feed.push_str(&format!("<title>{}</title>\n", escape(&self.title)));
+        feed.push_str(&format!("<link href=\"{}\"/>\n", escape(&self.base_url)));
+        feed.push_str(&format!(
+            "<link rel=\"self\" href=\"{}feed.xml\"/>\n",
+            escape(&self.base_url)
+        ));
+        feed.push_str(&format!("<id>{}</id>\n", escape(&self.base_url)));
+        feed.push_str(&format!("<updated>{}</updated>\n", rfc3339(updated)));
+        // Atom needs an author for every entry; this one stands in for posts
+        // that don't list any.
+        feed.push_str(&format!(
+            "<author><name>{}</name></author>\n",
+            escape(&self.title)
+        ));
+
+        for &(slug, post) in posts {
+            let url = escape(&format!("{}posts/{}.html", self.base_url, slug));
+
+            feed.push_str("<entry>\n");
+            feed.push_str(&format!("<title>{}</title>\n", escape(title(post))));
+            feed.push_str(&format!("<link href=\"{}\"/>\n", url));
+            feed.push_str(&format!("<id>{}</id>\n", url));
+            feed.push_str(&format!(
+                "<published>{}</published>\n",
+                rfc3339(published_at(post))
+            ));
+            feed.push_str(&format!(
+                "<updated>{}</updated>\n",
+                rfc3339(updated_at(post))
+            ));
+            for author in post.authors() {
+                feed.push_str(&format!(
+                    "<author><name>{}</name></author>\n",
+                    escape(author)
+                ));
+            }
+            for tag in post.tags() {
+                feed.push_str(&format!("<category term=\"{}\"/>\n", escape(tag)));
+            }
+            feed.push_str(&format!(
+                "<content type=\"html\">{}</content>\n",
+                escape(&post.render_html())
+            ));
+            feed.push_str("</entry>\n");
+        }
+
+        feed.push_str("</feed>\n");
+        feed
+    }
+}
+
+fn title(post: &Post) -> &str {
+    match post.title() {
+        "" => "Untitled",
+        title => title,
+    }
+}
+
+/// When the post went live: the last time it was published, according to
+/// its audit log.
+fn published_at(post: &Post) -> SystemTime {
+    post.audit_log()
+        .iter()
+        .rev()
+        .find(|entry| entry.to == Status::Published)
+        .map(|entry| entry.at)
+        // A scheduled post that is due hasn't logged its publication yet.
+        .or(post.publish_at())
+        .unwrap_or(post.updated_at())
+}
+
+fn updated_at(post: &Post) -> SystemTime {
+    post.updated_at().max(published_at(post))
+}
+
+/// Replaces every `{{name}}` in `template` with the value called `name`.
+/// Placeholders without a value are left as they are, and values are never
+/// searched for placeholders themselves.
+pub fn render(template: &str, values: &[(&str, &str)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}").map(|end| start + end + 2) else {
+            break;
+        };
+
+        let name = rest[start + 2..end - 2].trim();
+        out.push_str(&rest[..start]);
+        match values.iter().find(|(key, _)| *key == name) {
+            Some((_, value)) => out.push_str(value),
+            None => out.push_str(&rest[start..end]),
+        }
+        rest = &rest[end..];
+    }
+
+    out.push_str(rest);
+    out
+}
+
+/// Escapes text for use in HTML or XML, including in attribute values.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Formats a time as in RFC 3339, in UTC: `2024-03-01T12:00:00Z`.
+fn rfc3339(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (year, month, day) = civil_from_days(secs / 86_400);
+    let secs = secs % 86_400;
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+fn date(time: SystemTime) -> String {
+    rfc3339(time)[..10].to_string()
+}
+
+/// Converts days since 1970-01-01 into a (year, month, day) date in the
+/// proleptic Gregorian calendar, following
+/// <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    // Shift the epoch to 0000-03-01, so that leap days end a year.
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn renders_templates() {
+        assert_eq!(
+            "<h1>Hi &amp; bye</h1> {{missing}} {{",
+            render(
+                "<h1>{{ title }}</h1> {{missing}} {{",
+                &[("title", &escape("Hi & bye"))]
+            )
+        );
+        // A value that looks like a placeholder stays as it is.
+        assert_eq!(
+            "{{b}}!",
+            render("{{a}}{{b}}", &[("a", "{{b}}"), ("b", "!")])
+        );
+    }
+
+    #[test]
+    fn formats_times() {
+        assert_eq!("1970-01-01T00:00:00Z", rfc3339(UNIX_EPOCH));
+        assert_eq!(
+            "2024-02-29T13:45:30Z",
+            rfc3339(UNIX_EPOCH + Duration::from_secs(1_709_214_330))
+        );
+        assert_eq!(
+            "2000-03-01",
+            date(UNIX_EPOCH + Duration::from_secs(951_868_800))
+        );
+    }
+}
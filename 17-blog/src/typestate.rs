@@ -0,0 +1,324 @@
+//! A type-state version of the post workflow.
+//!
+//! Each state is its own type and every transition consumes the post and
+//! returns the type of the next state, so calling a method that doesn't make
+//! sense in the current state is a compile error rather than a silent no-op.
+//! Only a [`PublishedPost`] has content to show:
+//!
+//! ```
+//! use blog::{policy::Actor, typestate::{Approval, DraftPost}};
+//!
+//! let mut post = DraftPost::new();
+//! post.add_author("author");
+//! post.add_text("I ate a salad for lunch today");
+//!
+//! let post = post.request_review();
+//! let post = match post.approve(&Actor::editor("alice")) {
+//!     Ok(Approval::Published(post)) => post,
+//!     _ => unreachable!("one approval is enough"),
+//! };
+//! assert_eq!("I ate a salad for lunch today", post.content());
+//! ```
+//!
+//! Approvals are checked against the [`DefaultPolicy`], so nobody can approve
+//! a post they wrote. A draft can't be approved at all:
+//!
+//! ```compile_fail
+//! let post = blog::typestate::DraftPost::new();
+//! post.approve(&blog::policy::Actor::editor("alice"));
+//! ```
+//!
+//! and a post under review has no content to show yet:
+//!
+//! ```compile_fail
+//! let post = blog::typestate::DraftPost::new().request_review();
+//! post.content();
+//! ```
+//!
+//! Use [`Post`] instead when the sequence of actions is only known at runtime,
+//! e.g. when it comes from user input. Every type-state post converts into a
+//! [`Post`] in the matching state.
+
+use std::collections::BTreeSet;
+
+use crate::{
+    error::PermissionError,
+    lifecycle::Context,
+    policy::{Actor, DefaultPolicy, Policy},
+    Action, Post, Status,
+};
+
+#[derive(Debug)]
+pub struct DraftPost {
+    content: String,
+    authors: Vec<String>,
+    required_approvals: usize,
+}
+
+impl Default for DraftPost {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DraftPost {
+    pub fn new() -> DraftPost {
+        DraftPost::with_required_approvals(1)
+    }
+
+    /// Creates a draft that needs `required_approvals` different reviewers to
+    /// approve it before it is published. A post always needs at least one.
+    pub fn with_required_approvals(required_approvals: usize) -> DraftPost {
+        DraftPost {
+            content: String::new(),
+            authors: Vec::new(),
+            required_approvals: required_approvals.max(1),
+        }
+    }
+
+    /// Adds `author` to the end of the author list, unless they are on it
+    /// already.
+    pub fn add_author(&mut self, author: &str) {
+        if !self.authors.iter().any(|a| a == author) {
+            self.authors.push(author.to_string());
+        }
+    }
+
+    pub fn add_text(&mut self, text: &str) {
+        self.content.push_str(text);
+    }
+
+    pub fn request_review(self) -> PendingReviewPost {
+        PendingReviewPost {
+            content: self.content,
+            authors: self.authors,
+            required_approvals: self.required_approvals,
+            approvals: BTreeSet::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PendingReviewPost {
+    content: String,
+    authors: Vec<String>,
+    required_approvals: usize,
+    approvals: BTreeSet<String>,
+}
+
+/// The result of approving a post: whether it got enough approvals to be
+/// published or is still waiting for more.
+#[derive(Debug)]
+pub enum Approval {
+    Pending(PendingReviewPost),
+    Published(PublishedPost),
+}
+
+/// Returned when the policy doesn't let a reviewer approve a post, together
+/// with the post, unchanged.
+#[derive(Debug)]
+pub struct Refused {
+    pub post: PendingReviewPost,
+    pub error: PermissionError,
+}
+
+impl PendingReviewPost {
+    /// Records an approval from `reviewer`, if the [`DefaultPolicy`] lets
+    /// them approve the post. Approving twice as the same reviewer counts
+    /// once.
+    pub fn approve(mut self, reviewer: &Actor) -> Result<Approval, Refused> {
+        // The policy judges a Post, so it is shown one in the same state.
+        let post = Post::from(PendingReviewPost {
+            content: self.content.clone(),
+            authors: self.authors.clone(),
+            required_approvals: self.required_approvals,
+            approvals: self.approvals.clone(),
+        });
+        if !DefaultPolicy.permits(reviewer, Action::Approve, &post) {
+            return Err(Refused {
+                post: self,
+                error: PermissionError {
+                    actor: reviewer.clone(),
+                    action: Action::Approve,
+                },
+            });
+        }
+
+        self.approvals.insert(reviewer.name.clone());
+
+        if self.approvals.len() >= self.required_approvals {
+            Ok(Approval::Published(PublishedPost {
+                content: self.content,
+                authors: self.authors,
+            }))
+        } else {
+            Ok(Approval::Pending(self))
+        }
+    }
+
+    /// Sends the post back to draft, discarding the approvals so far.
+    pub fn reject(self) -> DraftPost {
+        DraftPost {
+            content: self.content,
+            authors: self.authors,
+            required_approvals: self.required_approvals,
+        }
+    }
+
+    /// The reviewers who have approved the post so far.
+    pub fn approvals(&self) -> Vec<&str> {
+        self.approvals.iter().map(String::as_str).collect()
+    }
+}
+
+#[derive(Debug)]
+pub struct PublishedPost {
+    content: String,
+    authors: Vec<String>,
+}
+
+impl PublishedPost {
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+}
+
+impl From<DraftPost> for Post {
+    fn from(post: DraftPost) -> Post {
+        with_authors(
+            Post::with_state(
+                Status::Draft,
+                post.content,
+                Context::new(post.required_approvals),
+            ),
+            &post.authors,
+        )
+    }
+}
+
+impl From<PendingReviewPost> for Post {
+    fn from(post: PendingReviewPost) -> Post {
+        with_authors(
+            Post::with_state(
+                Status::PendingReview,
+                post.content,
+                Context {
+                    approvals: post.approvals,
+                    ..Context::new(post.required_approvals)
+                },
+            ),
+            &post.authors,
+        )
+    }
+}
+
+impl From<PublishedPost> for Post {
+    fn from(post: PublishedPost) -> Post {
+        with_authors(
+            Post::with_state(Status::Published, post.content, Context::new(1)),
+            &post.authors,
+        )
+    }
+}
+
+fn with_authors(mut post: Post, authors: &[String]) -> Post {
+    for author in authors {
+        post.add_author(author);
+    }
+    post
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending(approval: Approval) -> PendingReviewPost {
+        match approval {
+            Approval::Pending(post) => post,
+            Approval::Published(_) => panic!("published too early"),
+        }
+    }
+
+    fn published(approval: Approval) -> PublishedPost {
+        match approval {
+            Approval::Published(post) => post,
+            Approval::Pending(_) => panic!("not published"),
+        }
+    }
+
+    #[test]
+    fn requires_distinct_reviewers() {
+        let mut post = DraftPost::with_required_approvals(2);
+        post.add_text("I ate a salad for lunch today");
+
+        let post = pending(
+            post.request_review()
+                .approve(&Actor::editor("alice"))
+                .unwrap(),
+        );
+        let post = pending(post.approve(&Actor::editor("alice")).unwrap());
+        assert_eq!(vec!["alice"], post.approvals());
+
+        let post = published(post.approve(&Actor::editor("bob")).unwrap());
+        assert_eq!("I ate a salad for lunch today", post.content());
+    }
+
+    #[test]
+    fn authors_cannot_approve_their_own_posts() {
+        let mut post = DraftPost::new();
+        post.add_author("alice");
+        post.add_text("I ate a salad for lunch today");
+
+        let refused = post
+            .request_review()
+            .approve(&Actor::editor("alice"))
+            .unwrap_err();
+        assert_eq!(
+            "editor alice may not approve this post",
+            refused.error.to_string()
+        );
+        assert!(refused.post.approvals().is_empty());
+
+        let post = published(refused.post.approve(&Actor::editor("bob")).unwrap());
+        assert_eq!(["alice"], Post::from(post).authors());
+    }
+
+    #[test]
+    fn reject_returns_a_draft() {
+        let mut post = DraftPost::with_required_approvals(2);
+        post.add_text("I ate a salad");
+
+        let mut post = pending(
+            post.request_review()
+                .approve(&Actor::editor("alice"))
+                .unwrap(),
+        )
+        .reject();
+        post.add_text(" for lunch today");
+
+        let post = pending(
+            post.request_review()
+                .approve(&Actor::editor("alice"))
+                .unwrap(),
+        );
+        assert_eq!(vec!["alice"], post.approvals());
+    }
+
+    #[test]
+    fn converts_into_dynamic_post() {
+        let mut draft = DraftPost::with_required_approvals(2);
+        draft.add_text("I ate a salad for lunch today");
+
+        let mut post = Post::from(pending(
+            draft
+                .request_review()
+                .approve(&Actor::editor("alice"))
+                .unwrap(),
+        ));
+        assert_eq!(vec!["alice"], post.approvals());
+        assert_eq!(1, post.revisions().len());
+
+        post.approve(&Actor::editor("bob")).unwrap();
+        assert_eq!("I ate a salad for lunch today", post.content());
+    }
+}
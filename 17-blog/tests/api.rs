@@ -0,0 +1,248 @@
+use std::{fs, thread};
+
+use blog::{
+    api::{self, Api, MAX_BODY_BYTES},
+    repository::{FileRepository, InMemoryRepository, PostRepository},
+    Post,
+};
+use serde_json::{json, Value};
+use ureq::Agent;
+
+/// Starts a server on a free port and returns its base URL.
+fn start_server() -> String {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", server.server_addr());
+
+    thread::spawn(move || {
+        let mut api = Api::new(InMemoryRepository::new());
+        api::serve(&server, &mut api);
+    });
+
+    url
+}
+
+struct Client {
+    url: String,
+    agent: Agent,
+}
+
+impl Client {
+    fn new() -> Client {
+        let agent = Agent::config_builder()
+            .http_status_as_error(false)
+            .build()
+            .into();
+
+        Client {
+            url: start_server(),
+            agent,
+        }
+    }
+
+    fn get(&self, path: &str) -> (u16, Value) {
+        let mut response = self
+            .agent
+            .get(format!("{}{}", self.url, path))
+            .call()
+            .unwrap();
+        let status = response.status().as_u16();
+        (status, response.body_mut().read_json().unwrap())
+    }
+
+    fn post(&self, path: &str, body: Value) -> (u16, Value) {
+        let mut response = self
+            .agent
+            .post(format!("{}{}", self.url, path))
+            .send_json(body)
+            .unwrap();
+        let status = response.status().as_u16();
+        (status, response.body_mut().read_json().unwrap())
+    }
+}
+
+#[test]
+fn publishes_a_post() {
+    let client = Client::new();
+
+    let (status, post) = client.post(
+        "/posts",
+        json!({
+            "authors": ["author"],
+            "required_approvals": 2,
+            "actor": "author",
+            "role": "author",
+        }),
+    );
+    assert_eq!(201, status);
+    let id = post["id"].as_str().unwrap();
+
+    let (status, _) = client.post(
+        &format!("/posts/{}/text", id),
+        json!({ "text": "Hello", "actor": "author", "role": "author" }),
+    );
+    assert_eq!(200, status);
+
+    client.post(
+        &format!("/posts/{}/request-review", id),
+        json!({ "actor": "author", "role": "author" }),
+    );
+    client.post(
+        &format!("/posts/{}/approve", id),
+        json!({ "actor": "alice", "role": "editor" }),
+    );
+
+    let (_, post) = client.get(&format!("/posts/{}", id));
+    assert_eq!(
+        json!({
+            "id": id,
+            "status": "pending_review",
+            "content": "",
+            "authors": ["author"],
+            "approvals": ["alice"],
+        }),
+        post
+    );
+
+    let (status, post) = client.post(
+        &format!("/posts/{}/approve", id),
+        json!({ "actor": "bob", "role": "editor" }),
+    );
+    assert_eq!(200, status);
+    assert_eq!("published", post["status"]);
+    assert_eq!("Hello", post["content"]);
+
+    let (_, list) = client.get("/posts");
+    assert_eq!(1, list["posts"].as_array().unwrap().len());
+}
+
+#[test]
+fn rejects_and_reports_errors() {
+    let client = Client::new();
+
+    let (_, post) = client.post(
+        "/posts",
+        json!({ "text": "Hello", "authors": ["author"], "actor": "author", "role": "author" }),
+    );
+    let id = post["id"].as_str().unwrap();
+
+    let (status, error) = client.post(
+        &format!("/posts/{}/text", id),
+        json!({ "text": "!", "actor": "mallory", "role": "author" }),
+    );
+    assert_eq!(403, status);
+    assert_eq!("author mallory may not edit this post", error["error"]);
+    let (status, _) = client.post(&format!("/posts/{}/text", id), json!({ "text": "!" }));
+    assert_eq!(400, status);
+
+    let (status, error) = client.post(
+        &format!("/posts/{}/approve", id),
+        json!({ "actor": "alice", "role": "editor" }),
+    );
+    assert_eq!(409, status);
+    assert_eq!("cannot approve a post in the draft state", error["error"]);
+
+    let (status, error) = client.post(
+        &format!("/posts/{}/request-review", id),
+        json!({ "actor": "mallory", "role": "author" }),
+    );
+    assert_eq!(403, status);
+    assert_eq!(
+        "author mallory may not request review of this post",
+        error["error"]
+    );
+
+    client.post(
+        &format!("/posts/{}/request-review", id),
+        json!({ "actor": "author", "role": "author" }),
+    );
+    let (status, error) = client.post(
+        &format!("/posts/{}/text", id),
+        json!({ "text": "!", "actor": "author", "role": "author" }),
+    );
+    assert_eq!(409, status);
+    assert_eq!(
+        "cannot edit a post in the pending review state",
+        error["error"]
+    );
+
+    let (_, post) = client.post(
+        &format!("/posts/{}/reject", id),
+        json!({ "actor": "alice", "role": "editor" }),
+    );
+    assert_eq!("draft", post["status"]);
+
+    let (status, _) = client.post(&format!("/posts/{}/approve", id), json!({ "nobody": 1 }));
+    assert_eq!(400, status);
+
+    let (status, _) = client.get("/posts/42");
+    assert_eq!(404, status);
+}
+
+#[test]
+fn creates_posts_without_text() {
+    let mut api = Api::new(InMemoryRepository::new());
+
+    let response = api.handle(
+        "POST",
+        "/posts",
+        r#"{ "authors": ["author"], "actor": "author", "role": "author" }"#,
+    );
+    assert_eq!(201, response.status);
+    let response = api.handle(
+        "POST",
+        "/posts",
+        r#"{ "text": "Hello", "actor": "alice", "role": "editor" }"#,
+    );
+    assert_eq!(201, response.status);
+
+    let empty = api.repository().load("1").unwrap().unwrap();
+    assert!(empty.revisions().is_empty());
+    let hello = api.repository().load("2").unwrap().unwrap();
+    assert_eq!(1, hello.revisions().len());
+}
+
+#[test]
+fn refuses_large_bodies() {
+    let client = Client::new();
+
+    let text = "a".repeat(MAX_BODY_BYTES);
+    let (status, error) = client.post(
+        "/posts",
+        json!({ "text": text, "actor": "alice", "role": "editor" }),
+    );
+    assert_eq!(413, status);
+    assert_eq!(
+        format!("request body larger than {} bytes", MAX_BODY_BYTES),
+        error["error"]
+    );
+
+    let (_, list) = client.get("/posts");
+    assert!(list["posts"].as_array().unwrap().is_empty());
+}
+
+#[test]
+fn does_not_reuse_ids() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut repository = FileRepository::open(dir.path()).unwrap();
+    repository.save("1", &Post::new()).unwrap();
+    repository.save("7", &Post::new()).unwrap();
+    repository.save("draft", &Post::new()).unwrap();
+
+    let mut api = Api::new(repository);
+    let mut create = || {
+        let response = api.handle(
+            "POST",
+            "/posts",
+            r#"{ "actor": "alice", "role": "editor" }"#,
+        );
+        assert_eq!(201, response.status);
+        response.body["id"].as_str().unwrap().to_string()
+    };
+
+    assert_eq!("8", create());
+    assert_eq!("9", create());
+
+    // The newest post goes away, but its id isn't handed out again.
+    fs::remove_file(dir.path().join("9.json")).unwrap();
+    assert_eq!("10", create());
+}
@@ -0,0 +1,120 @@
+use std::{fs, process::Command, sync::Arc, time::Duration, time::SystemTime};
+
+use blog::{
+    clock::ManualClock,
+    policy::Actor,
+    repository::{FileRepository, InMemoryRepository, PostRepository},
+    site::Site,
+    Post,
+};
+
+fn post(title: &str, text: &str, tags: &[&str], days: u64) -> Post {
+    let at = SystemTime::UNIX_EPOCH + Duration::from_secs(days * 86_400);
+    let mut post = Post::new().with_clock(Arc::new(ManualClock::new(at)));
+
+    post.add_author("alice");
+    post.set_title(&Actor::author("alice"), title).unwrap();
+    post.add_text(&Actor::author("alice"), text).unwrap();
+    for tag in tags {
+        post.add_tag(tag);
+    }
+    post
+}
+
+fn publish(mut post: Post) -> Post {
+    post.request_review(&Actor::author("alice")).unwrap();
+    post.approve(&Actor::editor("bob")).unwrap();
+    post
+}
+
+fn sample_repository(repository: &mut dyn PostRepository) {
+    let posts = [
+        (
+            "first",
+            publish(post("Hello <world>", "*Hi*", &["Rust"], 1)),
+        ),
+        (
+            "second",
+            publish(post("Hello world", "Again", &["rust", "news"], 2)),
+        ),
+        ("draft", post("Secret", "Not yet", &["rust"], 3)),
+    ];
+
+    for (id, post) in posts {
+        repository.save(id, &post).unwrap();
+    }
+}
+
+#[test]
+fn builds_pages_for_published_posts() {
+    let mut repository = InMemoryRepository::new();
+    sample_repository(&mut repository);
+
+    let out = tempfile::tempdir().unwrap();
+    let site = Site::new("Engineering", "https://example.com/blog");
+    assert_eq!(2, site.build(&repository, out.path()).unwrap());
+
+    let read = |path: &str| fs::read_to_string(out.path().join(path)).unwrap();
+
+    // Newest first; the older post keeps the plain slug.
+    let index = read("index.html");
+    let second = index.find("posts/hello-world-2.html").unwrap();
+    let first = index.find("posts/hello-world.html").unwrap();
+    assert!(second < first);
+    assert!(index.contains("Hello &lt;world&gt;"));
+    assert!(index.contains("<time>1970-01-02</time>"));
+    assert!(!index.contains("Secret"));
+
+    let page = read("posts/hello-world.html");
+    assert!(page.contains("<p><em>Hi</em></p>"));
+    assert!(page.contains("<a href=\"../tags/rust.html\">rust</a>"));
+    assert!(page.contains("by alice"));
+
+    let tag = read("tags/rust.html");
+    assert!(tag.contains("../posts/hello-world.html"));
+    assert!(tag.contains("../posts/hello-world-2.html"));
+    assert!(read("tags/news.html").contains("hello-world-2"));
+
+    let feed = read("feed.xml");
+    assert!(feed.contains("<id>https://example.com/blog/posts/hello-world.html</id>"));
+    assert!(feed.contains("<published>1970-01-03T00:00:00Z</published>"));
+    assert!(feed.contains("<content type=\"html\">&lt;p&gt;&lt;em&gt;Hi"));
+    assert_eq!(2, feed.matches("<entry>").count());
+}
+
+#[test]
+fn build_command() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut repository = FileRepository::open(dir.path().join("posts")).unwrap();
+    sample_repository(&mut repository);
+
+    fs::create_dir(dir.path().join("templates")).unwrap();
+    fs::write(
+        dir.path().join("templates/item.html"),
+        "<li>{{title}}</li>\n",
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_blog"))
+        .arg("build")
+        .arg(dir.path().join("posts"))
+        .arg(dir.path().join("site"))
+        .args(["--title", "Engineering"])
+        .arg("--templates")
+        .arg(dir.path().join("templates"))
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("Built 2 posts into "));
+
+    let index = fs::read_to_string(dir.path().join("site/index.html")).unwrap();
+    assert!(index.contains("<li>Hello world</li>"));
+    assert!(index.contains("<title>Engineering</title>"));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_blog"))
+        .args(["build", "only-one-path"])
+        .output()
+        .unwrap();
+    assert_eq!(Some(1), output.status.code());
+}